@@ -0,0 +1,22 @@
+//! Build script of the backend
+//!
+//! With the `embed-frontend` feature the frontend is built first, as a build dependency,
+//! and its output has to be there to be compiled into the binary
+
+use std::path::Path;
+
+const FRONTEND_OUTPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../frontend/frontend");
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    if std::env::var_os("CARGO_FEATURE_EMBED_FRONTEND").is_none() {
+        return;
+    }
+
+    println!("cargo:rerun-if-changed={FRONTEND_OUTPUT}");
+    let index = Path::new(FRONTEND_OUTPUT).join("index.html");
+    assert!(
+        index.is_file(),
+        "the frontend build output {index:?} is missing, it's required by the `embed-frontend` feature"
+    );
+}
@@ -1,28 +1,41 @@
-use crate::prelude::*;
-use cli::{Config, LoggerConfig};
+use crate::{
+    prelude::*,
+    request_id::X_REQUEST_ID,
+    services::logging::{LogFilters, FILE_OUTPUT, STDOUT_OUTPUT},
+};
+use cli::{Config, LogFormat, LoggerConfig};
 use file_rotate::{suffix::AppendTimestamp, ContentLimit, FileRotate};
 use thiserror::Error;
-use tower_http::cors::CorsLayer;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
+use tracing::Subscriber;
 use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
-use tracing_subscriber::{filter::LevelFilter, prelude::*, util::SubscriberInitExt};
+use tracing_subscriber::{
+    fmt::MakeWriter, prelude::*, registry::LookupSpan, reload, util::SubscriberInitExt, EnvFilter,
+    Layer,
+};
 
 #[allow(clippy::module_name_repetitions)]
 pub trait ConfigExt {
-    /// Return either very permissive [`CORS`](`CorsLayer`) configuration
-    /// or empty one based on `cors_allow_all` field
+    /// Return [`CORS`](`CorsLayer`) configuration of the `cors` section:
+    /// very permissive one if `allow-all` is set, or empty one if no origins are allowed.
+    /// The request id header is exposed to the allowed origins
     fn get_cors_configuration(&self) -> CorsLayer;
 }
 
 pub trait LoggerExt {
     /// Initialize logger.
     ///
-    /// Returns [`WorkerGuard`]s for off-thread writers.
-    /// Should not be dropped.
+    /// Returns [`WorkerGuard`]s for off-thread writers, which should not be dropped,
+    /// and the handles to change the outputs' filters at runtime
     ///
     /// # Errors
     ///
-    /// Function returns error if `init_file_rotate` fails
-    fn init_logger(&self) -> Result<Vec<WorkerGuard>, LoggerError>;
+    /// Function returns error if `init_file_rotate` fails or filter directives are invalid
+    fn init_logger(&self) -> Result<(Vec<WorkerGuard>, LogFilters), LoggerError>;
+
+    /// Filter directives of the output: its own ones if specified,
+    /// otherwise `RUST_LOG` or, if it's not set, the logger's ones
+    fn directives(&self, output_filter: Option<&str>) -> String;
 
     /// Returns [`std:io::Write`] object that rotates files on write
     ///
@@ -56,39 +69,101 @@ pub trait LoggerExt {
 
 impl ConfigExt for Config {
     fn get_cors_configuration(&self) -> CorsLayer {
-        self.cors_allow_all
-            .then_some(CorsLayer::very_permissive())
-            .unwrap_or_default()
+        let cors = &self.cors;
+        if cors.allow_all {
+            return CorsLayer::very_permissive().expose_headers([X_REQUEST_ID.clone()]);
+        }
+        if cors.origins.is_empty() {
+            return CorsLayer::default();
+        }
+
+        // Wildcards are mirrored with credentials, as browsers reject `*` then
+        let any = |values: &[String]| values.iter().any(|value| value == "*");
+        let methods = if any(&cors.methods) {
+            if cors.credentials {
+                AllowMethods::mirror_request()
+            } else {
+                AllowMethods::any()
+            }
+        } else {
+            AllowMethods::list(
+                cors.methods
+                    .iter()
+                    .filter_map(|method| method.to_uppercase().parse().ok()),
+            )
+        };
+        let headers = if any(&cors.headers) {
+            if cors.credentials {
+                AllowHeaders::mirror_request()
+            } else {
+                AllowHeaders::any()
+            }
+        } else {
+            AllowHeaders::list(cors.headers.iter().filter_map(|header| header.parse().ok()))
+        };
+        let layer = CorsLayer::new()
+            .allow_origin(allow_origin(&cors.origins, cors.credentials))
+            .allow_methods(methods)
+            .allow_headers(headers)
+            .allow_credentials(cors.credentials)
+            .expose_headers([X_REQUEST_ID.clone()]);
+
+        match cors.max_age {
+            Some(max_age) => layer.max_age(max_age),
+            None => layer,
+        }
     }
 }
 
 impl LoggerExt for LoggerConfig {
-    fn init_logger(&self) -> Result<Vec<WorkerGuard>, LoggerError> {
+    fn init_logger(&self) -> Result<(Vec<WorkerGuard>, LogFilters), LoggerError> {
         let mut guards = Vec::with_capacity(2);
+        let filters = LogFilters::default();
 
-        let file_writer = disable_on_error(self.non_blocking_file_writer())?;
-        let stdout_writer = disable_on_error(self.non_blocking_stdout_writer())?;
-
-        let mut layers_iter =
-            [file_writer, stdout_writer]
-                .into_iter()
-                .flatten()
-                .map(|(writer, guard)| {
-                    guards.push(guard);
-                    tracing_subscriber::fmt::layer()
-                        .with_writer(writer)
-                        .with_filter(LevelFilter::from_level(self.trace_level))
-                });
-
-        if let Some(first_layer) = layers_iter.next() {
-            tracing_subscriber::registry()
-                .with(layers_iter.fold(first_layer.boxed(), |layer, next_layer| {
-                    layer.and_then(next_layer).boxed()
-                }))
-                .init();
-        };
+        let file_output = self.file.as_ref().map(|file| {
+            (
+                FILE_OUTPUT,
+                file.format,
+                self.directives(file.filter.as_deref()),
+            )
+        });
+        let stdout_output = self.stdout.as_ref().map(|stdout| {
+            (
+                STDOUT_OUTPUT,
+                stdout.format,
+                self.directives(stdout.filter.as_deref()),
+            )
+        });
+        let file_writer = disable_on_error(self.non_blocking_file_writer())?.zip(file_output);
+        let stdout_writer = disable_on_error(self.non_blocking_stdout_writer())?.zip(stdout_output);
+
+        let mut layers = Vec::with_capacity(2);
+        for ((writer, guard), (output, format, directives)) in
+            [file_writer, stdout_writer].into_iter().flatten()
+        {
+            let (filter, handle) = reload::Layer::new(env_filter(&directives)?);
+            guards.push(guard);
+            filters.add(output, directives, handle);
+            layers.push(fmt_layer(writer, format).with_filter(filter).boxed());
+        }
+
+        if !layers.is_empty() {
+            tracing_subscriber::registry().with(layers).init();
+        }
+
+        Ok((guards, filters))
+    }
 
-        Ok(guards)
+    fn directives(&self, output_filter: Option<&str>) -> String {
+        output_filter.map_or_else(
+            || {
+                std::env::var(EnvFilter::DEFAULT_ENV)
+                    .ok()
+                    .filter(|directives| !directives.is_empty())
+                    .unwrap_or_else(|| self.filter.clone())
+            },
+            ToString::to_string,
+        )
     }
 
     fn init_file_rotate(&self) -> Result<FileRotate<AppendTimestamp>, LoggerError> {
@@ -142,6 +217,84 @@ pub enum LoggerError {
     NoFileName,
     #[error("This logger is not enabled")]
     NotEnabled,
+    #[error("Invalid log filter directives")]
+    InvalidFilter,
+}
+
+/// Parses the filter directives
+///
+/// # Errors
+///
+/// This function will return an error if the directives are invalid
+pub fn env_filter(directives: &str) -> Result<EnvFilter, LoggerError> {
+    EnvFilter::try_new(directives)
+        .change_context(LoggerError::InvalidFilter)
+        .attach_printable_lazy(|| format!("directives: {directives}"))
+}
+
+/// Formatting layer that writes the events to the `writer` in the `format`
+///
+/// Lines include the target module and the fields of the spans the event happened in
+fn fmt_layer<S, W>(writer: W, format: LogFormat) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_target(true);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+        LogFormat::Compact => layer.compact().boxed(),
+    }
+}
+
+/// Allowed origins of the exact ones and the patterns
+fn allow_origin(origins: &[String], credentials: bool) -> AllowOrigin {
+    if origins.iter().any(|origin| origin == "*") {
+        return if credentials {
+            AllowOrigin::mirror_request()
+        } else {
+            AllowOrigin::any()
+        };
+    }
+    if !origins.iter().any(|origin| origin.contains('*')) {
+        return AllowOrigin::list(origins.iter().filter_map(|origin| origin.parse().ok()));
+    }
+
+    let origins = origins.to_vec();
+    AllowOrigin::predicate(move |origin, _| {
+        origin.to_str().is_ok_and(|origin| {
+            origins
+                .iter()
+                .any(|pattern| origin_matches(pattern, origin))
+        })
+    })
+}
+
+/// Whether the `origin` matches the `pattern`, where `*` matches any part of the origin
+fn origin_matches(pattern: &str, origin: &str) -> bool {
+    let mut parts: Vec<_> = pattern.split('*').collect();
+    let last = parts.pop().unwrap_or_default();
+    let Some((first, middle)) = parts.split_first() else {
+        return origin == last;
+    };
+    let Some(mut rest) = origin.strip_prefix(first) else {
+        return false;
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.len() >= last.len() && rest.ends_with(last)
 }
 
 /// Consume some errors to produce empty logger
@@ -156,3 +309,197 @@ fn disable_on_error(
         },
     })
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use std::{fs::File, sync::Arc};
+
+    #[test]
+    fn json_format() {
+        let path = std::env::temp_dir().join(format!(
+            "bob-management-json-format-{}.log",
+            std::process::id()
+        ));
+        let subscriber = tracing_subscriber::registry().with(fmt_layer(
+            Arc::new(File::create(&path).unwrap()),
+            LogFormat::Json,
+        ));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("request", id = "req-1").in_scope(|| tracing::info!("done"));
+        });
+
+        let logs = std::fs::read(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let line: serde_json::Value = serde_json::from_slice(&logs).unwrap();
+        assert_eq!(line["target"], "bob_management::config::tests");
+        assert_eq!(line["fields"]["message"], "done");
+        assert_eq!(line["span"]["id"], "req-1");
+        assert_eq!(line["spans"][0]["name"], "request");
+    }
+
+    #[test]
+    fn output_filters() {
+        let logger = LoggerConfig {
+            filter: "warn".to_string(),
+            ..LoggerConfig::default()
+        };
+
+        assert_eq!(
+            logger.directives(Some("debug,hyper=info")),
+            "debug,hyper=info"
+        );
+        if std::env::var(EnvFilter::DEFAULT_ENV).is_err() {
+            assert_eq!(logger.directives(None), "warn");
+        }
+        assert!(env_filter("bob_management=debug").is_ok());
+        assert!(matches!(
+            env_filter("bob_management=loud")
+                .unwrap_err()
+                .current_context(),
+            LoggerError::InvalidFilter
+        ));
+    }
+
+    #[test]
+    fn origin_patterns() {
+        for (pattern, origin, matches) in [
+            ("http://localhost:3000", "http://localhost:3000", true),
+            ("http://localhost:3000", "http://localhost:3001", false),
+            ("http://localhost:*", "http://localhost:3001", true),
+            (
+                "https://*.example.com",
+                "https://ui.staging.example.com",
+                true,
+            ),
+            ("https://*.example.com", "https://example.com", false),
+            ("https://*.example.com", "http://ui.example.com", false),
+            (
+                "https://*.example.com",
+                "https://ui.example.com.evil",
+                false,
+            ),
+            (
+                "https://*-*.example.com",
+                "https://ui-dev.example.com",
+                true,
+            ),
+            ("https://*-*.example.com", "https://ui.example.com", false),
+        ] {
+            assert_eq!(
+                origin_matches(pattern, origin),
+                matches,
+                "{pattern} ~ {origin}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn cors_section() {
+        use axum::{routing::get, Router};
+        use cli::CorsConfig;
+        use hyper::{header::*, Body, Method, Request};
+        use std::time::Duration;
+        use tower::ServiceExt;
+
+        let config = Config {
+            cors: CorsConfig {
+                origins: vec![
+                    "http://localhost:3000".to_string(),
+                    "https://*.example.com".to_string(),
+                ],
+                credentials: true,
+                max_age: Some(Duration::from_secs(600)),
+                ..CorsConfig::default()
+            },
+            ..Config::default()
+        };
+        let router = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(config.get_cors_configuration());
+        let preflight = |origin: &str| {
+            Request::builder()
+                .method(Method::OPTIONS)
+                .uri("/")
+                .header(ORIGIN, origin)
+                .header(ACCESS_CONTROL_REQUEST_METHOD, "PUT")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get("/")
+                    .header(ORIGIN, "http://localhost:3000")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[ACCESS_CONTROL_EXPOSE_HEADERS],
+            "x-request-id"
+        );
+
+        for origin in ["http://localhost:3000", "https://ui.example.com"] {
+            let response = router.clone().oneshot(preflight(origin)).await.unwrap();
+            let headers = response.headers();
+            assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], origin);
+            assert_eq!(headers[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+            assert_eq!(
+                headers[ACCESS_CONTROL_ALLOW_METHODS],
+                "GET,POST,PUT,PATCH,DELETE"
+            );
+            assert_eq!(
+                headers[ACCESS_CONTROL_ALLOW_HEADERS],
+                "content-type,x-request-id"
+            );
+            assert_eq!(headers[ACCESS_CONTROL_MAX_AGE], "600");
+        }
+
+        let response = router
+            .oneshot(preflight("http://localhost:3001"))
+            .await
+            .unwrap();
+        assert!(response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn permissive_cors_exposes_request_id() {
+        use axum::{routing::get, Router};
+        use cli::CorsConfig;
+        use hyper::{header::*, Body, Request};
+        use tower::ServiceExt;
+
+        let config = Config {
+            cors: CorsConfig {
+                allow_all: true,
+                ..CorsConfig::default()
+            },
+            ..Config::default()
+        };
+        let response = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(config.get_cors_configuration())
+            .oneshot(
+                Request::get("/")
+                    .header(ORIGIN, "http://localhost:3000")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let headers = response.headers();
+
+        assert_eq!(
+            headers[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "http://localhost:3000"
+        );
+        assert_eq!(headers[ACCESS_CONTROL_EXPOSE_HEADERS], "x-request-id");
+    }
+}
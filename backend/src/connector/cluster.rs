@@ -0,0 +1,184 @@
+use super::{BobClient, ClientError, Credentials};
+use crate::{prelude::*, reload::RuntimeConfig};
+use cli::BootstrapNode;
+use hyper::StatusCode;
+use std::{collections::BTreeMap, sync::Arc};
+
+/// Clients of every node of the Bob cluster
+#[derive(Clone, Debug)]
+pub struct ClusterClient {
+    bootstrap: BobClient,
+    nodes: Arc<BTreeMap<String, BobClient>>,
+}
+
+impl ClusterClient {
+    #[must_use]
+    pub fn new(bootstrap: BobClient, nodes: impl IntoIterator<Item = (String, BobClient)>) -> Self {
+        Self {
+            bootstrap,
+            nodes: Arc::new(nodes.into_iter().collect()),
+        }
+    }
+
+    /// Discovers the cluster from the `bootstrap` node's `/nodes` response
+    ///
+    /// Bob reports gRPC addresses of the nodes, so REST API of every node
+    /// is expected on the same port as the bootstrap node's one.
+    /// Discovered clients share credentials and timeout of the `bootstrap` client
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the bootstrap node couldn't list the nodes
+    /// or reported an invalid address
+    pub async fn discover(bootstrap: BobClient) -> Result<Self, ClientError> {
+        let port = bootstrap.address().port_u16();
+        let nodes = bootstrap
+            .get_nodes()
+            .await?
+            .into_iter()
+            .map(|node| {
+                let host = node
+                    .address
+                    .rsplit_once(':')
+                    .map_or(node.address.as_str(), |(host, _)| host);
+                let address =
+                    port.map_or_else(|| host.to_string(), |port| format!("{host}:{port}"));
+                Ok((node.name, bootstrap.with_address(&address)?))
+            })
+            .collect::<Result<Vec<_>, ClientError>>()?;
+
+        Ok(Self::new(bootstrap, nodes))
+    }
+
+    /// Discovers the cluster from the first available bootstrap node.
+    /// The clients take the timeout of every request from the `runtime` configuration
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if none of the bootstrap nodes could list the nodes.
+    /// Nodes are not tried further once one of them rejected the `credentials`
+    pub async fn connect(
+        bootstrap: &[BootstrapNode],
+        credentials: Option<Credentials>,
+        runtime: &RuntimeConfig,
+    ) -> Result<Self, ClientError> {
+        let mut last_error = None;
+        for node in bootstrap {
+            let mut client = BobClient::try_new(&node.address, runtime.request_timeout())?
+                .with_runtime_timeout(runtime);
+            if let Some(credentials) = &credentials {
+                client = client.with_credentials(credentials.clone());
+            }
+            match Self::discover(client).await {
+                Ok(cluster) => return Ok(cluster),
+                Err(err) => {
+                    if let ClientError::UnexpectedStatus(
+                        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN,
+                    ) = err.current_context()
+                    {
+                        return Err(err);
+                    }
+                    tracing::warn!("bootstrap node {} is unavailable: {err:?}", node.address);
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            Report::new(ClientError::RequestFailed).attach_printable("no bootstrap nodes")
+        }))
+    }
+
+    /// Client of the node the cluster was discovered from
+    #[must_use]
+    pub const fn bootstrap(&self) -> &BobClient {
+        &self.bootstrap
+    }
+
+    /// Clients of the cluster's nodes, ordered by node name
+    pub fn nodes(&self) -> impl Iterator<Item = (&str, &BobClient)> {
+        self.nodes
+            .iter()
+            .map(|(name, client)| (name.as_str(), client))
+    }
+
+    #[must_use]
+    pub fn node(&self, name: &str) -> Option<&BobClient> {
+        self.nodes.get(name)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::connector::mock::MockNode;
+    use std::{net::TcpListener, time::Duration};
+
+    #[tokio::test]
+    async fn discover_nodes() {
+        let mut mock = MockNode::new("node1", &[0]);
+        mock.nodes = vec![mock.status.clone(), MockNode::new("node2", &[0]).status];
+        mock.nodes[1].address = "10.0.0.2:20000".to_string();
+        let addr = mock.spawn();
+
+        let cluster = ClusterClient::discover(
+            BobClient::try_new(&addr.to_string(), Duration::from_secs(1)).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            cluster
+                .nodes()
+                .map(|(name, client)| (name, client.address().to_string()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("node1", format!("127.0.0.1:{}", addr.port())),
+                ("node2", format!("10.0.0.2:{}", addr.port()))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn connect_skips_unavailable_nodes() {
+        let offline = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let online = MockNode::new("node1", &[0]).spawn();
+        let bootstrap = [offline, online].map(|addr| BootstrapNode {
+            address: addr.to_string(),
+            grpc_port: None,
+        });
+
+        let config = |request_timeout| cli::Config {
+            request_timeout,
+            ..cli::Config::default()
+        };
+        let runtime = RuntimeConfig::new(&config(Duration::from_secs(1)));
+
+        let cluster = ClusterClient::connect(&bootstrap, None, &runtime)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            cluster.bootstrap().address().port_u16(),
+            Some(online.port())
+        );
+        let node = cluster.node("node1").unwrap();
+        assert_eq!(node.timeout(), Duration::from_secs(1));
+        runtime.update(&config(Duration::from_secs(3)));
+        assert_eq!(node.timeout(), Duration::from_secs(3));
+    }
+}
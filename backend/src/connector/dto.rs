@@ -0,0 +1,134 @@
+//! Data transfer objects of the Bob's REST API
+//!
+//! These types mirror the responses of the Bob node as is.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Bob node, as returned by `/status` and `/nodes`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Node {
+    pub name: String,
+
+    /// gRPC address of the node <host:port>
+    pub address: String,
+
+    #[serde(default)]
+    pub vdisks: Vec<VDisk>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VDisk {
+    pub id: u64,
+
+    #[serde(default)]
+    pub replicas: Vec<Replica>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replica {
+    pub node: String,
+
+    pub disk: String,
+
+    pub path: String,
+}
+
+/// Physical disk state, as returned by `/disks/list`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiskState {
+    pub name: String,
+
+    pub path: String,
+
+    pub is_active: bool,
+}
+
+/// Partitions of the vdisk on the node, as returned by `/vdisks/{vdisk_id}/partitions`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VDiskPartitions {
+    pub vdisk_id: u64,
+
+    pub node_name: String,
+
+    pub disk_name: String,
+
+    #[serde(default)]
+    pub partitions: Vec<String>,
+}
+
+/// Single partition, as returned by `/vdisks/{vdisk_id}/partitions/{partition_id}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Partition {
+    pub vdisk_id: u64,
+
+    pub node_name: String,
+
+    pub disk_name: String,
+
+    pub timestamp: u64,
+
+    pub records_count: u64,
+
+    /// Size of the partition's data in bytes, not reported by every Bob version
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+/// Directory tree, as returned by `/alien/dir`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dir {
+    pub name: String,
+
+    pub path: String,
+
+    /// Size of the directory's content in bytes, not reported by every Bob version
+    #[serde(default)]
+    pub size: Option<u64>,
+
+    #[serde(default)]
+    pub children: Vec<Self>,
+}
+
+/// Disk space usage, as returned by `/status/space`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpaceInfo {
+    pub total_disk_space_bytes: u64,
+
+    pub free_disk_space_bytes: u64,
+
+    pub used_disk_space_bytes: u64,
+
+    pub occupied_disk_space_bytes: u64,
+
+    #[serde(default)]
+    pub occupied_disk_space_by_disk: HashMap<String, u64>,
+}
+
+/// Snapshot of the node's internal counters, as returned by `/metrics`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    #[serde(default)]
+    pub counters: HashMap<String, u64>,
+
+    #[serde(default)]
+    pub gauges: HashMap<String, i64>,
+
+    #[serde(default)]
+    pub times: HashMap<String, u64>,
+}
+
+/// Bob and Pearl versions, as returned by `/version`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub bob_version: Version,
+
+    pub pearl_version: Version,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Version {
+    pub version: String,
+
+    pub build_time: String,
+}
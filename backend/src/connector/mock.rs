@@ -0,0 +1,246 @@
+//! Mock Bob nodes for tests
+
+#![allow(clippy::unwrap_used)]
+
+use super::{dto, BobClient};
+use axum::{
+    extract::Path,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use hyper::{body::to_bytes, Body, Method, Request, StatusCode};
+use std::{
+    collections::HashMap,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tower::ServiceExt;
+
+/// Spawns `router` on a random port of `127.0.0.1` and returns its address
+pub fn spawn_node(router: Router) -> SocketAddr {
+    spawn_node_at(SocketAddr::from(([127, 0, 0, 1], 0)), router)
+}
+
+/// Spawns `router` on the specified address and returns the bound one
+pub fn spawn_node_at(addr: SocketAddr, router: Router) -> SocketAddr {
+    let server = axum::Server::bind(&addr).serve(router.into_make_service());
+    let addr = server.local_addr();
+    tokio::spawn(server);
+
+    addr
+}
+
+/// Spawns a node that accepts requests but never responds to them
+pub fn spawn_hanging_node() -> SocketAddr {
+    spawn_node(Router::new().fallback(std::future::pending::<()>))
+}
+
+/// Address that nobody listens to
+pub fn offline_address() -> String {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .to_string()
+}
+
+/// Client of the node at `addr` with a short timeout
+pub fn client(addr: &str) -> BobClient {
+    BobClient::try_new(addr, Duration::from_millis(500)).unwrap()
+}
+
+/// Sends the bodyless request to the `app`, returning the response's status and body
+pub async fn send(app: &Router, method: Method, uri: &str) -> (StatusCode, Vec<u8>) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+
+    (
+        status,
+        to_bytes(response.into_body()).await.unwrap().to_vec(),
+    )
+}
+
+/// State of a healthy mock node
+#[derive(Debug, Clone)]
+pub struct MockNode {
+    pub status: dto::Node,
+    pub nodes: Vec<dto::Node>,
+    pub disks: Vec<dto::DiskState>,
+    pub space: dto::SpaceInfo,
+    pub version: dto::VersionInfo,
+    pub alien_dir: dto::Dir,
+    /// Partitions of every vdisk, shared with the spawned node
+    pub partitions: Arc<Mutex<Vec<dto::Partition>>>,
+}
+
+impl MockNode {
+    /// Node `name` that hosts a replica of every vdisk in `vdisks` on `disk1`
+    pub fn new(name: &str, vdisks: &[u64]) -> Self {
+        let status = dto::Node {
+            name: name.to_string(),
+            address: "127.0.0.1:20000".to_string(),
+            vdisks: vdisks
+                .iter()
+                .map(|id| dto::VDisk {
+                    id: *id,
+                    replicas: vec![replica(name, "disk1", *id)],
+                })
+                .collect(),
+        };
+
+        Self {
+            nodes: vec![status.clone()],
+            status,
+            disks: vec![dto::DiskState {
+                name: "disk1".to_string(),
+                path: "/bob/d1".to_string(),
+                is_active: true,
+            }],
+            space: dto::SpaceInfo {
+                total_disk_space_bytes: 100,
+                free_disk_space_bytes: 60,
+                used_disk_space_bytes: 40,
+                occupied_disk_space_bytes: 30,
+                occupied_disk_space_by_disk: HashMap::from([("disk1".to_string(), 30)]),
+            },
+            version: dto::VersionInfo {
+                bob_version: dto::Version {
+                    version: "2.1.0".to_string(),
+                    build_time: "-".to_string(),
+                },
+                pearl_version: dto::Version {
+                    version: "0.20.0".to_string(),
+                    build_time: "-".to_string(),
+                },
+            },
+            alien_dir: dto::Dir {
+                name: "alien".to_string(),
+                path: "/bob/d1/alien".to_string(),
+                size: None,
+                children: vec![],
+            },
+            partitions: Arc::default(),
+        }
+    }
+
+    /// Adds partition of the vdisk stored on `disk1`
+    pub fn with_partition(self, vdisk_id: u64, timestamp: u64, records_count: u64) -> Self {
+        self.partitions.lock().unwrap().push(dto::Partition {
+            vdisk_id,
+            node_name: self.status.name.clone(),
+            disk_name: "disk1".to_string(),
+            timestamp,
+            records_count,
+            size: None,
+        });
+        self
+    }
+
+    pub fn router(self) -> Router {
+        let vdisks: Vec<_> = self.status.vdisks.clone();
+        let vdisk = {
+            let vdisks = vdisks.clone();
+            move |Path(vdisk_id): Path<u64>| async move {
+                vdisks
+                    .into_iter()
+                    .find(|vdisk| vdisk.id == vdisk_id)
+                    .map(Json)
+                    .ok_or(StatusCode::NOT_FOUND)
+            }
+        };
+        let partitions = self.partitions.clone();
+        let partitions_list = {
+            let partitions = partitions.clone();
+            let node_name = self.status.name.clone();
+            move |Path(vdisk_id): Path<u64>| async move {
+                Json(dto::VDiskPartitions {
+                    vdisk_id,
+                    node_name,
+                    disk_name: "disk1".to_string(),
+                    partitions: partitions
+                        .lock()
+                        .unwrap()
+                        .iter()
+                        .filter(|partition| partition.vdisk_id == vdisk_id)
+                        .map(|partition| partition.timestamp.to_string())
+                        .collect(),
+                })
+            }
+        };
+        let partition = {
+            let partitions = partitions.clone();
+            move |Path((vdisk_id, partition_id)): Path<(u64, String)>| async move {
+                partitions
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .find(|partition| {
+                        partition.vdisk_id == vdisk_id
+                            && partition.timestamp.to_string() == partition_id
+                    })
+                    .cloned()
+                    .map(Json)
+                    .ok_or(StatusCode::NOT_FOUND)
+            }
+        };
+        let delete_partition = move |Path((vdisk_id, timestamp)): Path<(u64, u64)>| async move {
+            let mut partitions = partitions.lock().unwrap();
+            let count = partitions.len();
+            partitions.retain(|partition| {
+                partition.vdisk_id != vdisk_id || partition.timestamp != timestamp
+            });
+            if partitions.len() == count {
+                StatusCode::NOT_FOUND
+            } else {
+                StatusCode::OK
+            }
+        };
+
+        Router::new()
+            .route("/vdisks/:vdisk_id", get(vdisk))
+            .route("/vdisks/:vdisk_id/partitions", get(partitions_list))
+            .route("/vdisks/:vdisk_id/partitions/:partition_id", get(partition))
+            .route(
+                "/vdisks/:vdisk_id/partitions/by_timestamp/:timestamp",
+                delete(delete_partition),
+            )
+            .route("/status", get(move || async move { Json(self.status) }))
+            .route("/nodes", get(move || async move { Json(self.nodes) }))
+            .route("/vdisks", get(move || async move { Json(vdisks) }))
+            .route("/disks/list", get(move || async move { Json(self.disks) }))
+            .route(
+                "/status/space",
+                get(move || async move { Json(self.space) }),
+            )
+            .route("/version", get(move || async move { Json(self.version) }))
+            .route(
+                "/alien/dir",
+                get(move || async move { Json(self.alien_dir) }),
+            )
+            .route("/alien/sync", post(|| async { StatusCode::OK }))
+            .route("/alien/detach", post(|| async { StatusCode::OK }))
+    }
+
+    pub fn spawn(self) -> SocketAddr {
+        spawn_node(self.router())
+    }
+}
+
+pub fn replica(node: &str, disk: &str, vdisk_id: u64) -> dto::Replica {
+    dto::Replica {
+        node: node.to_string(),
+        disk: disk.to_string(),
+        path: format!("/bob/{disk}/{vdisk_id}"),
+    }
+}
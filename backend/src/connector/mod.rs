@@ -1 +1,579 @@
+use crate::{
+    deadline,
+    prelude::*,
+    reload::RuntimeConfig,
+    request_id::{self, X_REQUEST_ID},
+};
+use base64::Engine;
+use cli::ClusterConfig;
+use hyper::{
+    body::to_bytes,
+    client::HttpConnector,
+    header::{ACCEPT, AUTHORIZATION},
+    http::uri::{Authority, Scheme},
+    Body, Client, Method, Request, StatusCode, Uri,
+};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::de::DeserializeOwned;
+use std::{fmt::Display, time::Duration};
+use thiserror::Error;
 
+pub mod cluster;
+pub mod dto;
+
+#[cfg(test)]
+pub mod mock;
+
+pub use cluster::ClusterClient;
+
+/// Characters escaped in a path segment: all but the unreserved ones, except for `.`,
+/// so that `..` can't be a segment
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'~');
+
+/// Encodes the `value` to be a single segment of the request path
+fn segment(value: &str) -> impl Display + '_ {
+    utf8_percent_encode(value, PATH_SEGMENT)
+}
+
+/// Errors that happend during communication with the Bob node
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum ClientError {
+    #[error("Invalid node address")]
+    InvalidAddress,
+    #[error("Couldn't send request to the node")]
+    RequestFailed,
+    /// The node at the address didn't respond in time
+    #[error("Node {0} didn't respond in time")]
+    Timeout(String),
+    #[error("Node responded with unexpected status code: `{0}`")]
+    UnexpectedStatus(StatusCode),
+    #[error("Couldn't deserialize node's response")]
+    InvalidResponse,
+}
+
+/// Authorization of the Bob's user
+#[derive(Clone, PartialEq, Eq)]
+pub enum Credentials {
+    /// Basic-auth login and password
+    Basic { login: String, password: String },
+    /// Bearer token
+    Token(String),
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Basic { login, .. } => f
+                .debug_struct("Basic")
+                .field("login", login)
+                .field("password", &"*****")
+                .finish(),
+            Self::Token(_) => f.debug_tuple("Token").field(&"*****").finish(),
+        }
+    }
+}
+
+impl Credentials {
+    /// Returns credentials specified in the cluster configuration, if any
+    #[must_use]
+    pub fn from_config(config: &ClusterConfig) -> Option<Self> {
+        config
+            .credentials
+            .as_ref()
+            .map(|credentials| Self::Basic {
+                login: credentials.login.clone(),
+                password: credentials.password.clone(),
+            })
+            .or_else(|| config.token.clone().map(Self::Token))
+    }
+
+    fn header_value(&self) -> String {
+        match self {
+            Self::Basic { login, password } => {
+                let encoded =
+                    base64::engine::general_purpose::STANDARD.encode(format!("{login}:{password}"));
+                format!("Basic {encoded}")
+            }
+            Self::Token(token) => format!("Bearer {token}"),
+        }
+    }
+}
+
+/// HTTP client of a single Bob node's REST API
+#[derive(Clone, Debug)]
+pub struct BobClient {
+    authority: Authority,
+    client: Client<HttpConnector>,
+    credentials: Option<Credentials>,
+    timeout: Timeout,
+}
+
+/// Time given to the node to respond to a request
+#[derive(Clone, Debug)]
+enum Timeout {
+    Fixed(Duration),
+    /// The [request timeout](RuntimeConfig::request_timeout) at the moment of the request
+    Runtime(RuntimeConfig),
+}
+
+impl BobClient {
+    /// Creates client for the node with REST API at `address` <host:port>
+    ///
+    /// Every request made by this client fails with [`ClientError::Timeout`]
+    /// if the node doesn't respond within `timeout`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `address` is not a valid <host:port> pair
+    pub fn try_new(address: &str, timeout: Duration) -> Result<Self, ClientError> {
+        let authority = address
+            .parse::<Authority>()
+            .change_context(ClientError::InvalidAddress)
+            .attach_printable_lazy(|| format!("address: {address}"))?;
+
+        Ok(Self {
+            authority,
+            client: Client::new(),
+            credentials: None,
+            timeout: Timeout::Fixed(timeout),
+        })
+    }
+
+    /// Creates client for another node with the same credentials and timeout
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `address` is not a valid <host:port> pair
+    pub fn with_address(&self, address: &str) -> Result<Self, ClientError> {
+        Ok(Self {
+            authority: address
+                .parse::<Authority>()
+                .change_context(ClientError::InvalidAddress)
+                .attach_printable_lazy(|| format!("address: {address}"))?,
+            ..self.clone()
+        })
+    }
+
+    /// Take the timeout of every request from the `runtime` configuration,
+    /// so that its changes apply to this client and the ones created from it
+    #[must_use]
+    pub fn with_runtime_timeout(mut self, runtime: &RuntimeConfig) -> Self {
+        self.timeout = Timeout::Runtime(runtime.clone());
+        self
+    }
+
+    /// Use `credentials` for basic authorization on every request
+    #[must_use]
+    pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Address of the node's REST API <host:port>
+    #[must_use]
+    pub const fn address(&self) -> &Authority {
+        &self.authority
+    }
+
+    #[must_use]
+    pub const fn credentials(&self) -> Option<&Credentials> {
+        self.credentials.as_ref()
+    }
+
+    /// Time the node is currently given to respond
+    #[must_use]
+    pub fn timeout(&self) -> Duration {
+        match &self.timeout {
+            Timeout::Fixed(timeout) => *timeout,
+            Timeout::Runtime(runtime) => runtime.request_timeout(),
+        }
+    }
+
+    /// Returns the node itself along with its vdisks
+    ///
+    /// # Errors
+    ///
+    /// See [`BobClient::send`]
+    pub async fn get_status(&self) -> Result<dto::Node, ClientError> {
+        self.get("/status").await
+    }
+
+    /// Returns all nodes of the cluster, as seen by this node
+    ///
+    /// # Errors
+    ///
+    /// See [`BobClient::send`]
+    pub async fn get_nodes(&self) -> Result<Vec<dto::Node>, ClientError> {
+        self.get("/nodes").await
+    }
+
+    /// Returns all vdisks of the cluster
+    ///
+    /// # Errors
+    ///
+    /// See [`BobClient::send`]
+    pub async fn get_vdisks(&self) -> Result<Vec<dto::VDisk>, ClientError> {
+        self.get("/vdisks").await
+    }
+
+    /// # Errors
+    ///
+    /// See [`BobClient::send`]
+    pub async fn get_vdisk(&self, vdisk_id: u64) -> Result<dto::VDisk, ClientError> {
+        self.get(&format!("/vdisks/{vdisk_id}")).await
+    }
+
+    /// Returns physical disks of the node
+    ///
+    /// # Errors
+    ///
+    /// See [`BobClient::send`]
+    pub async fn get_disks(&self) -> Result<Vec<dto::DiskState>, ClientError> {
+        self.get("/disks/list").await
+    }
+
+    /// Returns partition names of the vdisk stored on the node
+    ///
+    /// # Errors
+    ///
+    /// See [`BobClient::send`]
+    pub async fn get_partitions(&self, vdisk_id: u64) -> Result<dto::VDiskPartitions, ClientError> {
+        self.get(&format!("/vdisks/{vdisk_id}/partitions")).await
+    }
+
+    /// # Errors
+    ///
+    /// See [`BobClient::send`]
+    pub async fn get_partition(
+        &self,
+        vdisk_id: u64,
+        partition_id: &str,
+    ) -> Result<dto::Partition, ClientError> {
+        self.get(&format!(
+            "/vdisks/{vdisk_id}/partitions/{}",
+            segment(partition_id)
+        ))
+        .await
+    }
+
+    /// Deletes the vdisk's partition with the specified timestamp
+    ///
+    /// # Errors
+    ///
+    /// See [`BobClient::send`]
+    pub async fn delete_partition(&self, vdisk_id: u64, timestamp: u64) -> Result<(), ClientError> {
+        self.delete(&format!(
+            "/vdisks/{vdisk_id}/partitions/by_timestamp/{timestamp}"
+        ))
+        .await
+    }
+
+    /// Returns directory tree of the node's aliens
+    ///
+    /// # Errors
+    ///
+    /// See [`BobClient::send`]
+    pub async fn get_alien_dir(&self) -> Result<dto::Dir, ClientError> {
+        self.get("/alien/dir").await
+    }
+
+    /// Starts pushing the aliens back to the nodes they belong to
+    ///
+    /// # Errors
+    ///
+    /// See [`BobClient::send`]
+    pub async fn sync_aliens(&self) -> Result<(), ClientError> {
+        self.post("/alien/sync").await
+    }
+
+    /// Detaches the alien partitions, so they are not accessed until the next sync
+    ///
+    /// # Errors
+    ///
+    /// See [`BobClient::send`]
+    pub async fn detach_aliens(&self) -> Result<(), ClientError> {
+        self.post("/alien/detach").await
+    }
+
+    /// # Errors
+    ///
+    /// See [`BobClient::send`]
+    pub async fn get_metrics(&self) -> Result<dto::MetricsSnapshot, ClientError> {
+        self.get("/metrics").await
+    }
+
+    /// # Errors
+    ///
+    /// See [`BobClient::send`]
+    pub async fn get_space(&self) -> Result<dto::SpaceInfo, ClientError> {
+        self.get("/status/space").await
+    }
+
+    /// # Errors
+    ///
+    /// See [`BobClient::send`]
+    pub async fn get_version(&self) -> Result<dto::VersionInfo, ClientError> {
+        self.get("/version").await
+    }
+
+    /// Stops the disk for maintenance
+    ///
+    /// # Errors
+    ///
+    /// See [`BobClient::send`]
+    pub async fn stop_disk(&self, disk_name: &str) -> Result<(), ClientError> {
+        self.post(&format!("/disks/{}/stop", segment(disk_name)))
+            .await
+    }
+
+    /// Starts previously stopped disk
+    ///
+    /// # Errors
+    ///
+    /// See [`BobClient::send`]
+    pub async fn start_disk(&self, disk_name: &str) -> Result<(), ClientError> {
+        self.post(&format!("/disks/{}/start", segment(disk_name)))
+            .await
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ClientError> {
+        let body = self.send(Method::GET, path).await?;
+        serde_json::from_slice(&body)
+            .change_context(ClientError::InvalidResponse)
+            .attach_printable_lazy(|| self.describe(&Method::GET, path))
+    }
+
+    async fn post(&self, path: &str) -> Result<(), ClientError> {
+        self.send(Method::POST, path).await.map(|_| ())
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), ClientError> {
+        self.send(Method::DELETE, path).await.map(|_| ())
+    }
+
+    /// Sends request to the node and returns the body of successful response
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the node couldn't be reached,
+    /// didn't respond within the timeout or responded with non-success status code.
+    /// Requests that returned the body of an unexpected format fail with [`ClientError::InvalidResponse`]
+    async fn send(&self, method: Method, path: &str) -> Result<Vec<u8>, ClientError> {
+        let uri = Uri::builder()
+            .scheme(Scheme::HTTP)
+            .authority(self.authority.clone())
+            .path_and_query(path)
+            .build()
+            .change_context(ClientError::InvalidAddress)
+            .attach_printable_lazy(|| self.describe(&method, path))?;
+        let mut request = Request::builder()
+            .method(method.clone())
+            .uri(uri)
+            .header(ACCEPT, "application/json");
+        if let Some(credentials) = &self.credentials {
+            request = request.header(AUTHORIZATION, credentials.header_value());
+        }
+        if let Some(id) = request_id::current() {
+            request = request.header(&X_REQUEST_ID, id);
+        }
+        let request = request
+            .body(Body::empty())
+            .change_context(ClientError::RequestFailed)
+            .attach_printable_lazy(|| self.describe(&method, path))?;
+
+        // The node isn't waited for past the deadline of the API request it's made for
+        let timeout = deadline::remaining().map_or(self.timeout(), |left| left.min(self.timeout()));
+        let response = tokio::time::timeout(timeout, async {
+            let response = self.client.request(request).await?;
+            let status = response.status();
+            Ok::<_, hyper::Error>((status, to_bytes(response.into_body()).await?))
+        })
+        .await
+        .change_context_lazy(|| ClientError::Timeout(self.authority.to_string()))
+        .attach_printable_lazy(|| self.describe(&method, path))?;
+        let (status, body) = response
+            .change_context(ClientError::RequestFailed)
+            .attach_printable_lazy(|| self.describe(&method, path))?;
+
+        if status.is_success() {
+            Ok(body.to_vec())
+        } else {
+            Err(ClientError::UnexpectedStatus(status))
+                .attach_printable_lazy(|| self.describe(&method, path))
+                .attach_printable_lazy(|| format!("response: {}", String::from_utf8_lossy(&body)))
+        }
+    }
+
+    fn describe(&self, method: &Method, path: impl Display) -> String {
+        format!("request: {method} {}{path}", self.authority)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::unused_async)]
+    use super::*;
+    use crate::connector::mock::spawn_node;
+    use axum::{extract::Path, http::HeaderMap, routing::get, Json, Router};
+
+    fn node(name: &str) -> dto::Node {
+        dto::Node {
+            name: name.to_string(),
+            address: "127.0.0.1:20000".to_string(),
+            vdisks: vec![dto::VDisk {
+                id: 0,
+                replicas: vec![dto::Replica {
+                    node: name.to_string(),
+                    disk: "disk1".to_string(),
+                    path: "/tmp/d1".to_string(),
+                }],
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn get_status() {
+        let addr =
+            spawn_node(Router::new().route("/status", get(|| async { Json(node("node1")) })));
+        let client = BobClient::try_new(&addr.to_string(), Duration::from_secs(1)).unwrap();
+
+        assert_eq!(client.get_status().await.unwrap(), node("node1"));
+    }
+
+    #[tokio::test]
+    async fn get_partition_with_path_arguments() {
+        let addr = spawn_node(Router::new().route(
+            "/vdisks/:vdisk_id/partitions/:partition_id",
+            get(
+                |Path((vdisk_id, partition_id)): Path<(u64, String)>| async move {
+                    Json(dto::Partition {
+                        vdisk_id,
+                        node_name: "node1".to_string(),
+                        disk_name: "disk1".to_string(),
+                        timestamp: partition_id.parse().unwrap(),
+                        records_count: 10,
+                        size: None,
+                    })
+                },
+            ),
+        ));
+        let client = BobClient::try_new(&addr.to_string(), Duration::from_secs(1)).unwrap();
+
+        let partition = client.get_partition(3, "1700000000").await.unwrap();
+        assert_eq!(partition.vdisk_id, 3);
+        assert_eq!(partition.timestamp, 1_700_000_000);
+    }
+
+    #[tokio::test]
+    async fn path_arguments_are_encoded() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let addr = spawn_node(Router::new().route(
+            "/disks/:disk_name/stop",
+            axum::routing::post(move |Path(disk_name): Path<String>| async move {
+                tx.send(disk_name).unwrap();
+            }),
+        ));
+        let client = BobClient::try_new(&addr.to_string(), Duration::from_secs(1)).unwrap();
+
+        for disk_name in ["x/../../y", "a?b", "..", "c#d e"] {
+            client.stop_disk(disk_name).await.unwrap();
+            assert_eq!(rx.recv().await.unwrap(), disk_name);
+        }
+    }
+
+    #[tokio::test]
+    async fn basic_auth_header() {
+        let addr = spawn_node(Router::new().route(
+            "/nodes",
+            get(|headers: HeaderMap| async move {
+                if headers.get(AUTHORIZATION).unwrap() == "Basic YWRtaW46cGFzc3dvcmQ=" {
+                    Ok(Json(vec![node("node1"), node("node2")]))
+                } else {
+                    Err(StatusCode::UNAUTHORIZED)
+                }
+            }),
+        ));
+        let client = BobClient::try_new(&addr.to_string(), Duration::from_secs(1))
+            .unwrap()
+            .with_credentials(Credentials::Basic {
+                login: "admin".to_string(),
+                password: "password".to_string(),
+            });
+
+        assert_eq!(client.get_nodes().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn request_id_header() {
+        let addr = spawn_node(Router::new().route(
+            "/nodes",
+            get(|headers: HeaderMap| async move {
+                Json(
+                    headers
+                        .get(&X_REQUEST_ID)
+                        .map(|id| vec![node(id.to_str().unwrap())])
+                        .unwrap_or_default(),
+                )
+            }),
+        ));
+        let client = BobClient::try_new(&addr.to_string(), Duration::from_secs(1)).unwrap();
+
+        assert!(client.get_nodes().await.unwrap().is_empty());
+        let nodes = request_id::scope("req-1".to_string(), client.get_nodes())
+            .await
+            .unwrap();
+        assert_eq!(nodes[0].name, "req-1");
+    }
+
+    #[tokio::test]
+    async fn unexpected_status() {
+        let addr = spawn_node(Router::new().route(
+            "/version",
+            get(|| async { (StatusCode::FORBIDDEN, "forbidden") }),
+        ));
+        let client = BobClient::try_new(&addr.to_string(), Duration::from_secs(1)).unwrap();
+
+        assert_eq!(
+            client.get_version().await.unwrap_err().current_context(),
+            &ClientError::UnexpectedStatus(StatusCode::FORBIDDEN)
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_response() {
+        let addr = spawn_node(Router::new().route("/status/space", get(|| async { "{}" })));
+        let client = BobClient::try_new(&addr.to_string(), Duration::from_secs(1)).unwrap();
+
+        assert_eq!(
+            client.get_space().await.unwrap_err().current_context(),
+            &ClientError::InvalidResponse
+        );
+    }
+
+    #[tokio::test]
+    async fn request_timeout() {
+        let addr = spawn_node(Router::new().route(
+            "/metrics",
+            get(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Json(dto::MetricsSnapshot::default())
+            }),
+        ));
+        let client = BobClient::try_new(&addr.to_string(), Duration::from_millis(50)).unwrap();
+
+        assert_eq!(
+            client.get_metrics().await.unwrap_err().current_context(),
+            &ClientError::Timeout(addr.to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_address() {
+        assert_eq!(
+            BobClient::try_new("not an address", Duration::from_secs(1))
+                .unwrap_err()
+                .current_context(),
+            &ClientError::InvalidAddress
+        );
+    }
+}
@@ -0,0 +1,42 @@
+//! Deadlines of the API requests
+//!
+//! An API request is given the [request timeout](crate::reload::RuntimeConfig::request_timeout)
+//! as a whole. The requests to the nodes made while processing it are cut off at its deadline,
+//! so that the handler still reports the nodes that didn't respond in time
+
+use std::future::Future;
+use tokio::time::{Duration, Instant};
+
+tokio::task_local! {
+    static DEADLINE: Instant;
+}
+
+/// Time left until the deadline of the request being processed, if any
+#[must_use]
+pub fn remaining() -> Option<Duration> {
+    DEADLINE
+        .try_with(|deadline| deadline.saturating_duration_since(Instant::now()))
+        .ok()
+}
+
+/// Runs `future` as a part of the request that has to be processed by the `deadline`
+pub async fn scope<F: Future>(deadline: Instant, future: F) -> F::Output {
+    DEADLINE.scope(deadline, future).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn remaining_time() {
+        assert_eq!(remaining(), None);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let left = scope(deadline, async { remaining() }).await;
+        assert!(left.is_some_and(|left| left > Duration::from_secs(9)));
+
+        let past = scope(Instant::now(), async { remaining() }).await;
+        assert_eq!(past, Some(Duration::ZERO));
+    }
+}
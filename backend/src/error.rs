@@ -1,9 +1,25 @@
 #![allow(clippy::module_name_repetitions)]
 
-use axum::response::{IntoResponse, Response};
+use crate::request_id;
+use axum::{
+    http::{
+        header::{CONTENT_LENGTH, CONTENT_TYPE},
+        HeaderValue, Request,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
 use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+#[cfg(all(feature = "swagger", debug_assertions))]
+use utoipa::{ToResponse, ToSchema};
+
+/// Media type of the [`ProblemDetails`] responses
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
 /// Server start up errors
 #[derive(Debug, Error)]
 pub enum AppError {
@@ -16,10 +32,235 @@ pub enum AppError {
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         tracing::error!("{}", self);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Something went wrong".to_string(),
-        )
+        ProblemDetails::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error")
+            .with_detail(self.to_string())
             .into_response()
     }
 }
+
+// Not exported by `tsync`, which ignores the renamed fields, see `frontend/src/types/problem.d.ts`
+/// Error response of the API, as described in RFC 7807
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(
+    all(feature = "swagger", debug_assertions),
+    derive(ToSchema, ToResponse)
+)]
+#[cfg_attr(
+    all(feature = "swagger", debug_assertions),
+    response(
+        description = "Error response",
+        content_type = "application/problem+json"
+    )
+)]
+pub struct ProblemDetails {
+    /// URI of the problem type, `about:blank` as the `code` identifies the problem
+    #[serde(rename = "type")]
+    pub problem_type: String,
+
+    /// Short summary of the problem, the reason phrase of the `status`
+    pub title: String,
+
+    /// HTTP status code
+    pub status: u16,
+
+    /// Stable machine-readable code of the problem
+    pub code: String,
+
+    /// Explanation specific to this occurrence of the problem
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+
+    /// Path of the request that caused the problem
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+
+    /// Name of the Bob node that caused the problem
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+
+    /// Id of the request, to find it in the logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl ProblemDetails {
+    /// Problem of the current request with the `status` and the `code`
+    #[must_use]
+    pub fn new(status: StatusCode, code: &str) -> Self {
+        Self {
+            problem_type: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            code: code.to_string(),
+            detail: None,
+            instance: None,
+            node: None,
+            request_id: request_id::current(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_instance(mut self, instance: impl Into<String>) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_node(mut self, node: impl Into<String>) -> Self {
+        self.node = Some(node.into());
+        self
+    }
+
+    /// Problem of the error response that isn't a problem yet, such as the extractors' rejections,
+    /// with the response's text as the detail
+    #[must_use]
+    pub fn from_status(status: StatusCode, text: &str) -> Self {
+        let code = match status {
+            StatusCode::UNAUTHORIZED => "unauthorized",
+            StatusCode::NOT_FOUND => "not_found",
+            StatusCode::METHOD_NOT_ALLOWED => "method_not_allowed",
+            status if status.is_client_error() => "invalid_request",
+            _ => "internal_error",
+        };
+        let problem = Self::new(status, code);
+        if text.trim().is_empty() {
+            problem
+        } else {
+            problem.with_detail(text.trim())
+        }
+    }
+}
+
+/// Turns the error responses of the `next` handlers that aren't problems into the ones,
+/// so that the clients get [`ProblemDetails`] for the rejected requests as well
+pub async fn problem_responses<B>(request: Request<B>, next: Next<B>) -> Response {
+    let response = next.run(request).await;
+    let status = response.status();
+    let is_problem = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|content_type| content_type == PROBLEM_JSON);
+    if !(status.is_client_error() || status.is_server_error()) || is_problem {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let text = hyper::body::to_bytes(body).await.unwrap_or_default();
+    let problem = ProblemDetails::from_status(status, &String::from_utf8_lossy(&text));
+    let problem = problem.into_response();
+    parts.headers.remove(CONTENT_LENGTH);
+    parts.headers.extend(problem.headers().clone());
+
+    Response::from_parts(parts, problem.into_body())
+}
+
+impl IntoResponse for ProblemDetails {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut response = (status, Json(self)).into_response();
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use hyper::body::to_bytes;
+
+    #[tokio::test]
+    async fn problem_response() {
+        let response = request_id::scope("req-1".to_string(), async {
+            ProblemDetails::new(StatusCode::BAD_GATEWAY, "node_error")
+                .with_node("node1")
+                .into_response()
+        })
+        .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(response.headers()[CONTENT_TYPE], PROBLEM_JSON);
+        let body: serde_json::Value =
+            serde_json::from_slice(&to_bytes(response.into_body()).await.unwrap()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "type": "about:blank",
+                "title": "Bad Gateway",
+                "status": 502,
+                "code": "node_error",
+                "node": "node1",
+                "request_id": "req-1",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn rejections() {
+        use axum::{
+            extract::{Json, Path},
+            middleware::from_fn,
+            routing::{get, post},
+            Router,
+        };
+        use tower::ServiceExt;
+
+        let router = Router::new()
+            .route(
+                "/ids/:id",
+                get(|Path(id): Path<u64>| async move { id.to_string() }),
+            )
+            .route(
+                "/echo",
+                post(|Json(body): Json<u64>| async move { body.to_string() }),
+            )
+            .route(
+                "/problem",
+                get(|| async { ProblemDetails::new(StatusCode::CONFLICT, "conflict") }),
+            )
+            .layer(from_fn(problem_responses));
+        let send = |request: Request<hyper::Body>| async {
+            let response = router.clone().oneshot(request).await.unwrap();
+            let status = response.status();
+            assert_eq!(response.headers()[CONTENT_TYPE], PROBLEM_JSON);
+            let body = to_bytes(response.into_body()).await.unwrap();
+            (
+                status,
+                serde_json::from_slice::<ProblemDetails>(&body).unwrap(),
+            )
+        };
+
+        let (status, problem) = send(Request::get("/ids/x").body("".into()).unwrap()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(problem.code, "invalid_request");
+        assert!(problem.detail.unwrap().contains("Cannot parse"));
+
+        let (status, problem) = send(
+            Request::post("/echo")
+                .header(CONTENT_TYPE, "application/json")
+                .body("\"x\"".into())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(problem.code, "invalid_request");
+
+        let (status, problem) = send(Request::post("/ids/1").body("".into()).unwrap()).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(problem.code, "method_not_allowed");
+        assert_eq!(problem.detail, None);
+
+        let (status, problem) = send(Request::get("/problem").body("".into()).unwrap()).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(problem.code, "conflict");
+    }
+}
@@ -0,0 +1,328 @@
+//! Frontend serving
+//!
+//! With the `embed-frontend` feature the Astro build output is compiled into the binary,
+//! files missing from it are looked up on disk.
+//! Client-side routes get `index.html`, while unknown `/api` paths get problem+json 404
+
+use crate::services::api_not_found;
+use axum::{
+    body::BoxBody,
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE},
+        HeaderValue, Request, StatusCode,
+    },
+    response::Response,
+    routing::any,
+    Router,
+};
+use hyper::Body;
+use std::path::PathBuf;
+use tower_http::services::ServeDir;
+
+#[cfg(feature = "embed-frontend")]
+pub use embedded::{serve, Assets};
+
+/// Directory of the Astro's bundled assets, which names contain the content hash
+const HASHED_ASSETS_DIR: &str = "/_astro/";
+
+/// Router that serves the frontend, using the `folder` on disk as a fallback
+pub fn frontend_router(folder: PathBuf) -> Router {
+    let serve_dir = ServeDir::new(folder)
+        .precompressed_br()
+        .precompressed_gzip();
+
+    Router::new()
+        .route("/api", any(api_not_found))
+        .route("/api/*path", any(api_not_found))
+        .fallback(move |request| serve_spa(request, serve_dir.clone()))
+}
+
+/// Serves the requested file, or `index.html` if the path is not a file's one
+/// and might be a client-side route
+async fn serve_spa(request: Request<Body>, serve_dir: ServeDir) -> Response {
+    let path = request.uri().path().to_string();
+    let index_request = (!is_asset(&path)).then(|| {
+        let mut index = Request::new(Body::empty());
+        *index.method_mut() = request.method().clone();
+        *index.uri_mut() = "/index.html".parse().unwrap_or_default();
+        *index.headers_mut() = request.headers().clone();
+        index
+    });
+
+    let mut response = serve_file(request, serve_dir.clone()).await;
+    if response.status() == StatusCode::NOT_FOUND {
+        if let Some(index_request) = index_request {
+            response = serve_file(index_request, serve_dir).await;
+        }
+    }
+    set_cache_control(&path, &mut response);
+
+    response
+}
+
+async fn serve_file(request: Request<Body>, serve_dir: ServeDir) -> Response {
+    #[cfg(feature = "embed-frontend")]
+    return serve::<Assets>(request, serve_dir).await;
+
+    #[cfg(not(feature = "embed-frontend"))]
+    {
+        use tower::ServiceExt;
+        match serve_dir.oneshot(request).await {
+            Ok(response) => response.map(axum::body::boxed),
+            Err(err) => match err {},
+        }
+    }
+}
+
+/// Whether the path points to a file, judging by the extension of its last segment
+fn is_asset(path: &str) -> bool {
+    path.rsplit('/')
+        .next()
+        .is_some_and(|name| name.contains('.'))
+}
+
+/// Hashed assets never change, so they are cached forever,
+/// while HTML is revalidated on every request to pick up the new assets
+fn set_cache_control(path: &str, response: &mut Response<BoxBody>) {
+    if !(response.status().is_success() || response.status() == StatusCode::NOT_MODIFIED) {
+        return;
+    }
+    let is_html = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+
+    if is_html {
+        response
+            .headers_mut()
+            .insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    } else if path.starts_with(HASHED_ASSETS_DIR) {
+        response.headers_mut().insert(
+            CACHE_CONTROL,
+            HeaderValue::from_static("public, max-age=31536000, immutable"),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use hyper::body::to_bytes;
+    use tower::ServiceExt;
+
+    async fn get(uri: &str) -> Response<BoxBody> {
+        frontend_router(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/frontend").into())
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn client_side_route() {
+        let response = get("/nodes/node1").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CACHE_CONTROL], "no-cache");
+        let body = to_bytes(response.into_body()).await.unwrap();
+        assert!(body.starts_with(b"<!doctype html>"));
+
+        let response = get("/assets/missing.js").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn unknown_api_path() {
+        let response = get("/api/v2/cluster").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[CONTENT_TYPE], crate::error::PROBLEM_JSON);
+    }
+
+    #[tokio::test]
+    async fn cache_control() {
+        let response = get("/_astro/index.3f2a9c1b.js").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[CACHE_CONTROL],
+            "public, max-age=31536000, immutable"
+        );
+
+        let response = get("/assets/app.js").await;
+        assert!(response.headers().get(CACHE_CONTROL).is_none());
+    }
+}
+
+#[cfg(feature = "embed-frontend")]
+mod embedded {
+    use axum::{
+        body::{boxed, Full},
+        http::{
+            header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE, ETAG, IF_NONE_MATCH, VARY},
+            HeaderMap, HeaderValue, Request, StatusCode,
+        },
+        response::{IntoResponse, Response},
+    };
+    use hyper::Body;
+    use rust_embed::RustEmbed;
+    use std::fmt::Write;
+    use tower::ServiceExt;
+    use tower_http::services::ServeDir;
+
+    /// Astro build output, embedded at compile time
+    #[derive(RustEmbed)]
+    #[folder = "../frontend/frontend"]
+    pub struct Assets;
+
+    /// Precompressed variants of the files, in the order of preference
+    const ENCODINGS: [(&str, &str); 2] = [("br", "br"), ("gzip", "gz")];
+
+    /// Serves the embedded file, or passes the request to the `fallback` if there is none
+    ///
+    /// Precompressed `.br` and `.gz` siblings of the file are served to the clients that accept them
+    pub async fn serve<E: RustEmbed>(request: Request<Body>, fallback: ServeDir) -> Response {
+        let path = request.uri().path().trim_start_matches('/');
+        let path = if path.is_empty() || path.ends_with('/') {
+            format!("{path}index.html")
+        } else {
+            path.to_string()
+        };
+        let Some(file) = E::get(&path) else {
+            return match fallback.oneshot(request).await {
+                Ok(response) => response.map(boxed),
+                Err(err) => match err {},
+            };
+        };
+
+        let mut headers = HeaderMap::new();
+        if let Ok(mime) = HeaderValue::from_str(file.metadata.mimetype()) {
+            headers.insert(CONTENT_TYPE, mime);
+        }
+        let variants: Vec<_> = ENCODINGS
+            .iter()
+            .filter_map(|(encoding, extension)| {
+                E::get(&format!("{path}.{extension}")).map(|file| (*encoding, file))
+            })
+            .collect();
+        if !variants.is_empty() {
+            headers.insert(VARY, HeaderValue::from_static("accept-encoding"));
+        }
+        let accepted = variants
+            .into_iter()
+            .find(|(encoding, _)| accepts(request.headers(), encoding));
+        let file = match accepted {
+            Some((encoding, variant)) => {
+                headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding));
+                variant
+            }
+            None => file,
+        };
+
+        let etag = etag(&file.metadata.sha256_hash());
+        let not_modified = request
+            .headers()
+            .get(IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag));
+        if let Ok(etag) = HeaderValue::from_str(&etag) {
+            headers.insert(ETAG, etag);
+        }
+
+        if not_modified {
+            (StatusCode::NOT_MODIFIED, headers).into_response()
+        } else {
+            (headers, Full::from(file.data)).into_response()
+        }
+    }
+
+    /// Whether `Accept-Encoding` allows the `encoding`
+    fn accepts(headers: &HeaderMap, encoding: &str) -> bool {
+        headers
+            .get_all(ACCEPT_ENCODING)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|entry| {
+                let mut params = entry.split(';').map(str::trim);
+                params.next() == Some(encoding)
+                    && !params.any(|param| {
+                        param
+                            .strip_prefix("q=")
+                            .and_then(|q| q.parse::<f32>().ok())
+                            .is_some_and(|q| q == 0.0)
+                    })
+            })
+    }
+
+    fn etag(hash: &[u8; 32]) -> String {
+        let mut etag = String::with_capacity(66);
+        etag.push('"');
+        for byte in hash {
+            let _ = write!(etag, "{byte:02x}");
+        }
+        etag.push('"');
+
+        etag
+    }
+
+    #[cfg(test)]
+    mod tests {
+        #![allow(clippy::unwrap_used)]
+        use super::*;
+        use axum::{body::BoxBody, Router};
+        use hyper::body::to_bytes;
+
+        #[derive(RustEmbed)]
+        #[folder = "tests/frontend"]
+        struct TestAssets;
+
+        fn router() -> Router {
+            let fallback = ServeDir::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fallback"));
+            Router::new().fallback(move |request| serve::<TestAssets>(request, fallback.clone()))
+        }
+
+        async fn get(uri: &str, headers: &[(&str, &str)]) -> Response<BoxBody> {
+            let mut request = Request::get(uri);
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            router()
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap()
+        }
+
+        #[tokio::test]
+        async fn embedded_file() {
+            let response = get("/", &[]).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()[CONTENT_TYPE], "text/html");
+            let etag = response.headers()[ETAG].to_str().unwrap().to_string();
+            assert_eq!(etag.len(), 66);
+
+            let response = get("/index.html", &[("if-none-match", &etag)]).await;
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        }
+
+        #[tokio::test]
+        async fn precompressed_variant() {
+            let response = get("/assets/app.js", &[("accept-encoding", "gzip, br;q=0")]).await;
+            assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+            assert_eq!(response.headers()[CONTENT_TYPE], "text/javascript");
+            assert_eq!(response.headers()[VARY], "accept-encoding");
+            let body = to_bytes(response.into_body()).await.unwrap();
+            assert_eq!(&body[..], b"gzipped app.js\n");
+
+            let response = get("/assets/app.js", &[]).await;
+            assert!(response.headers().get(CONTENT_ENCODING).is_none());
+        }
+
+        #[tokio::test]
+        async fn disk_fallback() {
+            let response = get("/on-disk.txt", &[]).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let response = get("/missing.txt", &[]).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+}
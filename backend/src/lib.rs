@@ -6,15 +6,73 @@ use axum::{routing::get, Router};
 use utoipa::OpenApi;
 pub mod config;
 pub mod connector;
+pub mod deadline;
 pub mod error;
+pub mod frontend;
 pub mod models;
+pub mod reload;
+pub mod request_id;
 pub mod router;
 pub mod services;
+pub mod shutdown;
+pub mod tls;
 
 #[derive(OpenApi)]
 #[cfg_attr(not(all(feature = "swagger", debug_assertions)), openapi())]
 #[cfg_attr(all(feature = "swagger", debug_assertions), openapi(
-    paths(root),
+    paths(
+        root,
+        services::api::get_cluster,
+        services::api::get_node,
+        services::disks::stop_disk,
+        services::disks::start_disk,
+        services::partitions::get_partitions,
+        services::partitions::delete_partition,
+        services::partitions::delete_old_partitions,
+        services::aliens::get_aliens_summary,
+        services::aliens::get_node_aliens,
+        services::aliens::sync_aliens,
+        services::aliens::detach_aliens,
+        services::metrics::get_cluster_metrics,
+        services::metrics::get_node_metrics,
+        services::logging::get_log_filters,
+        services::logging::set_log_filters,
+        services::auth::login,
+        services::auth::logout,
+    ),
+    components(
+        schemas(
+            error::ProblemDetails,
+            models::api::ClusterInfo,
+            models::api::ClusterSpace,
+            models::api::LoginRequest,
+            models::api::NodeDetails,
+            models::api::Node,
+            models::api::NodeStatus,
+            models::api::VDisk,
+            models::api::Replica,
+            models::api::Disk,
+            models::api::DiskStatus,
+            models::api::Alien,
+            models::api::SpaceInfo,
+            models::api::NodeVersion,
+            models::api::VDisksCount,
+            models::api::Partition,
+            models::api::ReplicaPartitions,
+            models::api::PartitionsDeletion,
+            models::api::NodeAliens,
+            models::api::RemoteNodeAliens,
+            models::api::AliensSummary,
+            models::api::MetricsSample,
+            models::api::NodeMetrics,
+            models::api::ClusterMetrics,
+            models::api::LogFilter,
+            models::api::LogFilterUpdate,
+        ),
+        responses(
+            error::ProblemDetails,
+        )
+    ),
     tags(
         (name = "bob", description = "BOB management API")
     )
@@ -65,10 +123,25 @@ pub fn openapi_doc() -> Router {
 
 pub mod prelude {
     #![allow(unused_imports)]
-    pub use crate::error::AppError;
+    pub use crate::error::{AppError, ProblemDetails};
     pub use crate::router::RouteError;
     pub use axum::response::Result as AxumResult;
     pub use error_stack::{Context, Report, Result, ResultExt};
     // #[cfg(all(feature = "swagger", debug_assertions))]
     pub use utoipa::OpenApi;
 }
+
+#[cfg(all(test, feature = "swagger", debug_assertions))]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn openapi_spec_is_up_to_date() {
+        assert!(
+            ApiDoc::openapi().to_yaml().unwrap() == include_str!("../../api/openapi.yaml"),
+            "api/openapi.yaml is outdated, regenerate it with \
+             `cargo run -p utils --bin gen-openapi -- -f api/openapi.yaml`"
+        );
+    }
+}
@@ -4,63 +4,156 @@
     clippy::expect_used
 )]
 
-use axum::Router;
+use axum::{
+    middleware::{from_fn, from_fn_with_state},
+    Extension, Router,
+};
 use bob_management::{
-    config::{ConfigExt, LoggerExt},
+    config::LoggerExt,
+    frontend::frontend_router,
     prelude::*,
-    root,
+    reload::{self, RuntimeConfig},
+    request_id, root,
     router::{ApiV1, ApiVersion, NoApi, RouterApiExt},
-    services::api_router_v1,
-    ApiDoc,
+    services::{
+        api_router_v1, auth::AuthState, logging::LogFilters, metrics::MetricsStore,
+        with_request_timeout,
+    },
+    shutdown::{serve_until, shutdown_signal},
+    tls, ApiDoc,
 };
 use cli::Parser;
 use error_stack::{Result, ResultExt};
 use hyper::Method;
 use std::env;
 use tower::ServiceBuilder;
-use tower_http::{cors::CorsLayer, services::ServeDir};
 
 const FRONTEND_FOLDER: &str = "frontend";
 
 #[tokio::main]
 async fn main() -> Result<(), AppError> {
-    let config = cli::Config::try_from(cli::Args::parse())
+    let args = match cli::Args::parse().command {
+        cli::Command::Serve(args) => args,
+        command => {
+            command
+                .run(&mut std::io::stdout())
+                .change_context(AppError::InitializationError)?;
+            return Ok(());
+        }
+    };
+    let config = cli::Config::try_from(args.clone())
         .change_context(AppError::InitializationError)
         .attach_printable("Couldn't get config file.")?;
 
     let logger = &config.logger;
 
-    let _guard = logger.init_logger().unwrap();
+    let (guards, log_filters) = logger.init_logger().unwrap();
     tracing::info!("Logger: {logger:?}");
 
-    let cors: CorsLayer = config.get_cors_configuration();
-    tracing::info!("CORS: {cors:?}");
+    let runtime = RuntimeConfig::new(&config);
+    tracing::info!("CORS: {:?}", runtime.cors());
 
     let addr = config.address;
     tracing::info!("Listening on {addr}");
 
-    let app = router(cors);
+    let metrics = MetricsStore::new(config.metrics.samples)
+        .with_stale_after(config.metrics.scrape_interval * 2);
+    let mut background_tasks = vec![
+        metrics
+            .clone()
+            .spawn_scraper(runtime.clone(), &config.metrics),
+        reload::spawn_reloader(args, config.clone(), runtime.clone(), log_filters.clone()),
+    ];
+
+    let app = router(
+        runtime.clone(),
+        AuthState::new(runtime).with_secure_cookie(config.tls.is_some()),
+        metrics,
+        log_filters,
+    );
     #[cfg(all(feature = "swagger", debug_assertions))]
     let app = app.merge(bob_management::openapi_doc());
 
-    axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+    if let Some(tls_config) = &config.tls {
+        let rustls = tls::load(tls_config)
+            .await
+            .change_context(AppError::InitializationError)?;
+        #[cfg(unix)]
+        background_tasks.push(tls::reload_on_sighup(rustls.clone(), tls_config.clone()));
+
+        if let Some(redirect) = tls_config.redirect_http {
+            tracing::info!("Redirecting HTTP requests from {redirect}");
+            let server = axum::Server::try_bind(&redirect)
+                .change_context(AppError::StartUpError)
+                .attach_printable("Failed to start HTTP redirect server")?
+                .serve(tls::redirect_router(addr.port()).into_make_service());
+            background_tasks.push(tokio::spawn(async move {
+                if let Err(err) = server.await {
+                    tracing::error!("HTTP redirect server failed: {err}");
+                }
+            }));
+        }
+
+        let handle = axum_server::Handle::new();
+        let server = axum_server::bind_rustls(addr, rustls)
+            .handle(handle.clone())
+            .serve(app.into_make_service());
+        serve_until(
+            |shutdown| async move {
+                tokio::spawn(async move {
+                    shutdown.await;
+                    handle.graceful_shutdown(None);
+                });
+                server.await
+            },
+            shutdown_signal(),
+            config.shutdown_timeout,
+        )
+        .await
+        .change_context(AppError::StartUpError)
+        .attach_printable("Axum server failed")?;
+    } else {
+        let server = axum::Server::try_bind(&addr)
+            .change_context(AppError::StartUpError)
+            .attach_printable("Failed to start axum server")?
+            .serve(app.into_make_service());
+        serve_until(
+            |shutdown| server.with_graceful_shutdown(shutdown),
+            shutdown_signal(),
+            config.shutdown_timeout,
+        )
         .await
         .change_context(AppError::StartUpError)
-        .attach_printable("Failed to start axum server")?;
+        .attach_printable("Axum server failed")?;
+    }
+
+    for task in background_tasks {
+        task.abort();
+    }
+    tracing::info!("Server stopped");
+    // Flush buffered log lines
+    drop(guards);
 
     Ok(())
 }
 
 #[allow(clippy::unwrap_used, clippy::expect_used)]
-fn router(cors: CorsLayer) -> Router {
+fn router(
+    runtime: RuntimeConfig,
+    auth: AuthState,
+    metrics: MetricsStore,
+    log_filters: LogFilters,
+) -> Router {
     let mut frontend = env::current_exe().expect("Couldn't get current executable path.");
     frontend.pop();
     frontend.push(FRONTEND_FOLDER);
+    #[cfg(feature = "embed-frontend")]
+    tracing::info!("serving embedded frontend, falling back to: {frontend:?}");
+    #[cfg(not(feature = "embed-frontend"))]
     tracing::info!("serving frontend at: {frontend:?}");
     let router = Router::new()
         // Frontend
-        .nest_service("/", ServeDir::new(frontend));
+        .nest_service("/", frontend_router(frontend));
 
     // Add API
     let router = router
@@ -72,9 +165,21 @@ fn router(cors: CorsLayer) -> Router {
     router
         .nest(
             ApiV1::to_path(),
-            api_router_v1().expect("couldn't get API routes"),
+            with_request_timeout(
+                bob_management::services::auth::with_auth(
+                    api_router_v1().expect("couldn't get API routes"),
+                    auth,
+                )
+                .layer(Extension(metrics))
+                .layer(Extension(log_filters)),
+                runtime.clone(),
+            ),
+        )
+        .layer(
+            ServiceBuilder::new()
+                .layer(from_fn(request_id::request_id))
+                .layer(from_fn_with_state(runtime, reload::cors)),
         )
-        .layer(ServiceBuilder::new().layer(cors))
 }
 
 #[cfg(test)]
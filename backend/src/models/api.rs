@@ -0,0 +1,584 @@
+use crate::{
+    connector::{dto, ClientError},
+    error::ProblemDetails,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tsync::tsync;
+
+#[cfg(all(feature = "swagger", debug_assertions))]
+use utoipa::ToSchema;
+
+/// Bob node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct Node {
+    pub name: String,
+
+    /// gRPC address of the node <host:port>
+    pub address: String,
+
+    pub status: NodeStatus,
+
+    pub vdisks: Vec<VDisk>,
+}
+
+/// Whether the node responds to requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+#[tsync]
+pub enum NodeStatus {
+    Online,
+    Offline,
+    /// The node didn't respond within the request timeout
+    Timeout,
+}
+
+/// Virtual disk and its replicas
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct VDisk {
+    pub id: u64,
+
+    pub replicas: Vec<Replica>,
+}
+
+/// Location of a vdisk's replica
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct Replica {
+    pub node: String,
+
+    pub disk: String,
+
+    pub path: String,
+}
+
+/// Physical disk of the node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct Disk {
+    pub name: String,
+
+    pub path: String,
+
+    pub status: DiskStatus,
+}
+
+/// Whether the disk is used by the node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+#[tsync]
+pub enum DiskStatus {
+    Active,
+    Inactive,
+}
+
+/// Partition of the vdisk on the node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct Partition {
+    pub vdisk_id: u64,
+
+    pub node: String,
+
+    pub disk: String,
+
+    /// Unix timestamp of the partition's start, in seconds
+    pub timestamp: u64,
+
+    pub records_count: u64,
+
+    /// Size of the partition's data in bytes, absent if the node doesn't report it
+    pub size: Option<u64>,
+}
+
+/// Data stored on the node on behalf of the unavailable `remote_node`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct Alien {
+    pub remote_node: String,
+
+    pub vdisk_id: u64,
+
+    pub path: String,
+
+    /// Size of the alien data in bytes, absent if the node doesn't report it
+    pub size: Option<u64>,
+}
+
+/// Disk space usage of the node, in bytes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct SpaceInfo {
+    pub total_disk: u64,
+
+    pub free_disk: u64,
+
+    pub used_disk: u64,
+
+    /// Space occupied by Bob's data
+    pub occupied_disk: u64,
+
+    /// Space occupied by Bob's data on each disk
+    pub occupied_by_disk: HashMap<String, u64>,
+}
+
+/// Health of the vdisk, based on availability of its replicas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+#[tsync]
+pub enum VDiskStatus {
+    /// Every replica is available
+    Good,
+    /// Some of the replicas are unavailable
+    Degraded,
+    /// None of the replicas are available
+    Offline,
+}
+
+/// Whole-cluster overview
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct ClusterInfo {
+    pub nodes_count: u64,
+
+    pub nodes_online: u64,
+
+    /// Names of the nodes that didn't respond within the request timeout
+    pub nodes_timed_out: Vec<String>,
+
+    pub vdisks: VDisksCount,
+
+    /// Disk space usage of the online nodes
+    pub space: ClusterSpace,
+
+    pub versions: Vec<NodeVersion>,
+}
+
+/// Number of vdisks of each [`VDiskStatus`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct VDisksCount {
+    pub good: u64,
+
+    pub degraded: u64,
+
+    pub offline: u64,
+}
+
+/// Disk space usage of the cluster, in bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct ClusterSpace {
+    pub total_disk: u64,
+
+    pub used_disk: u64,
+
+    pub free_disk: u64,
+}
+
+/// Versions of the node's software, absent for the offline nodes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct NodeVersion {
+    pub node: String,
+
+    pub bob_version: Option<String>,
+
+    pub pearl_version: Option<String>,
+}
+
+/// Detailed state of a single node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct NodeDetails {
+    pub name: String,
+
+    /// gRPC address of the node <host:port>, absent for the offline node
+    pub address: Option<String>,
+
+    pub status: NodeStatus,
+
+    pub disks: Vec<Disk>,
+
+    /// Vdisks which replicas are hosted by the node
+    pub vdisks: Vec<VDisk>,
+
+    pub aliens: Vec<Alien>,
+
+    pub space: Option<SpaceInfo>,
+
+    pub version: Option<NodeVersion>,
+}
+
+/// Partitions of the vdisk's replicas stored on the node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct ReplicaPartitions {
+    pub node: String,
+
+    pub status: NodeStatus,
+
+    /// Partitions ordered by timestamp, empty for the offline node
+    pub partitions: Vec<Partition>,
+}
+
+/// Result of the partitions deletion
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct PartitionsDeletion {
+    pub deleted: Vec<Partition>,
+
+    /// Partitions the nodes failed to delete
+    pub failed: Vec<Partition>,
+
+    /// Problems of the replica nodes that couldn't be reached,
+    /// whose partitions are left as they are
+    pub unreached: Vec<ProblemDetails>,
+}
+
+/// Aliens stored on the node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct NodeAliens {
+    pub node: String,
+
+    pub status: NodeStatus,
+
+    /// Empty for the offline node
+    pub aliens: Vec<Alien>,
+}
+
+/// Aliens waiting to be pushed back to the `remote_node`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct RemoteNodeAliens {
+    pub remote_node: String,
+
+    /// Number of vdisks with pending aliens
+    pub vdisks_count: u64,
+
+    /// Size of the pending aliens in bytes, of those which size is reported
+    pub size: u64,
+
+    /// Number of vdisks with pending aliens whose size the node didn't report,
+    /// they aren't included in `size`
+    pub unsized_vdisks_count: u64,
+}
+
+/// Whole-cluster summary of the pending aliens
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct AliensSummary {
+    /// Number of vdisks with pending aliens, across all nodes
+    pub vdisks_count: u64,
+
+    /// Size of the pending aliens in bytes, of those which size is reported
+    pub size: u64,
+
+    /// Number of vdisks with pending aliens whose size the node didn't report,
+    /// they aren't included in `size`
+    pub unsized_vdisks_count: u64,
+
+    /// Pending aliens grouped by the node they belong to, ordered by its name
+    pub remote_nodes: Vec<RemoteNodeAliens>,
+
+    /// Nodes that couldn't report their aliens
+    pub unavailable_nodes: Vec<String>,
+}
+
+/// Metrics of the node at the moment of scraping
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct MetricsSample {
+    /// Unix timestamp of the scrape, in milliseconds
+    pub timestamp: u64,
+
+    pub counters: HashMap<String, u64>,
+
+    pub gauges: HashMap<String, i64>,
+
+    /// Per-second increase of the counters since the previous sample,
+    /// empty for the first sample and missing for the counters that were reset
+    pub rates: HashMap<String, f64>,
+}
+
+/// Latest metrics samples of the node, ordered by time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct NodeMetrics {
+    pub node: String,
+
+    pub samples: Vec<MetricsSample>,
+}
+
+/// Latest metrics of every node, summed across the cluster
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct ClusterMetrics {
+    /// Number of nodes that have been scraped at least once
+    pub nodes_count: u64,
+
+    pub counters: HashMap<String, u64>,
+
+    pub gauges: HashMap<String, i64>,
+
+    pub rates: HashMap<String, f64>,
+}
+
+/// Active filter directives of a log output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct LogFilter {
+    /// `file` or `stdout`
+    pub output: String,
+
+    pub directives: String,
+
+    /// Directives specified in the configuration
+    pub configured: String,
+
+    /// Unix timestamp in milliseconds when the configured directives are restored
+    pub revert_at: Option<u64>,
+}
+
+/// Change of the log outputs' filter directives
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct LogFilterUpdate {
+    /// Output to change, every output is changed if not specified
+    #[serde(default)]
+    pub output: Option<String>,
+
+    pub directives: String,
+
+    /// Seconds after which the configured directives are restored, a week at most
+    #[serde(default)]
+    pub revert_after: Option<u64>,
+}
+
+/// Address of one of the cluster's nodes and the Bob user's credentials
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(all(feature = "swagger", debug_assertions), derive(ToSchema))]
+#[tsync]
+pub struct LoginRequest {
+    /// REST API address of the node <host:port>, accepted only if `auth.allow-hostname` is set
+    /// and no cluster is configured
+    #[serde(default)]
+    pub hostname: Option<String>,
+
+    pub login: String,
+
+    pub password: String,
+}
+
+impl Node {
+    #[must_use]
+    pub fn new(node: dto::Node, status: NodeStatus) -> Self {
+        Self {
+            name: node.name,
+            address: node.address,
+            status,
+            vdisks: node.vdisks.into_iter().map(VDisk::from).collect(),
+        }
+    }
+}
+
+impl NodeStatus {
+    /// Status of the node judging by the result of a request to it
+    #[must_use]
+    pub fn from_result<T>(result: &error_stack::Result<T, ClientError>) -> Self {
+        match result {
+            Ok(_) => Self::Online,
+            Err(err) if matches!(err.current_context(), ClientError::Timeout(_)) => Self::Timeout,
+            Err(_) => Self::Offline,
+        }
+    }
+}
+
+impl std::fmt::Debug for LoginRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoginRequest")
+            .field("hostname", &self.hostname)
+            .field("login", &self.login)
+            .field("password", &"*****")
+            .finish()
+    }
+}
+
+impl VDisksCount {
+    pub fn add(&mut self, status: VDiskStatus) {
+        match status {
+            VDiskStatus::Good => self.good += 1,
+            VDiskStatus::Degraded => self.degraded += 1,
+            VDiskStatus::Offline => self.offline += 1,
+        }
+    }
+}
+
+impl std::ops::AddAssign<&SpaceInfo> for ClusterSpace {
+    fn add_assign(&mut self, space: &SpaceInfo) {
+        self.total_disk += space.total_disk;
+        self.used_disk += space.used_disk;
+        self.free_disk += space.free_disk;
+    }
+}
+
+impl From<dto::VDisk> for VDisk {
+    fn from(vdisk: dto::VDisk) -> Self {
+        Self {
+            id: vdisk.id,
+            replicas: vdisk.replicas.into_iter().map(Replica::from).collect(),
+        }
+    }
+}
+
+impl From<dto::Replica> for Replica {
+    fn from(replica: dto::Replica) -> Self {
+        Self {
+            node: replica.node,
+            disk: replica.disk,
+            path: replica.path,
+        }
+    }
+}
+
+impl From<dto::DiskState> for Disk {
+    fn from(disk: dto::DiskState) -> Self {
+        Self {
+            name: disk.name,
+            path: disk.path,
+            status: if disk.is_active {
+                DiskStatus::Active
+            } else {
+                DiskStatus::Inactive
+            },
+        }
+    }
+}
+
+impl From<dto::Partition> for Partition {
+    fn from(partition: dto::Partition) -> Self {
+        Self {
+            vdisk_id: partition.vdisk_id,
+            node: partition.node_name,
+            disk: partition.disk_name,
+            timestamp: partition.timestamp,
+            records_count: partition.records_count,
+            size: partition.size,
+        }
+    }
+}
+
+impl From<dto::SpaceInfo> for SpaceInfo {
+    fn from(space: dto::SpaceInfo) -> Self {
+        Self {
+            total_disk: space.total_disk_space_bytes,
+            free_disk: space.free_disk_space_bytes,
+            used_disk: space.used_disk_space_bytes,
+            occupied_disk: space.occupied_disk_space_bytes,
+            occupied_by_disk: space.occupied_disk_space_by_disk,
+        }
+    }
+}
+
+impl Alien {
+    /// Collects aliens from the node's alien directory
+    ///
+    /// The directory is expected to be laid out as `<alien>/<remote_node>/<vdisk_id>`,
+    /// entries that don't follow this layout are skipped
+    #[must_use]
+    pub fn from_dir(alien_dir: &dto::Dir) -> Vec<Self> {
+        alien_dir
+            .children
+            .iter()
+            .flat_map(|node_dir| {
+                node_dir.children.iter().filter_map(|vdisk_dir| {
+                    Some(Self {
+                        remote_node: node_dir.name.clone(),
+                        vdisk_id: vdisk_dir.name.parse().ok()?,
+                        path: vdisk_dir.path.clone(),
+                        size: vdisk_dir.size,
+                    })
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir(name: &str, children: Vec<dto::Dir>) -> dto::Dir {
+        dto::Dir {
+            name: name.to_string(),
+            path: format!("/bob/alien/{name}"),
+            size: None,
+            children,
+        }
+    }
+
+    #[test]
+    fn aliens_from_dir() {
+        let alien_dir = dir(
+            "alien",
+            vec![
+                dir("node2", vec![dir("0", vec![]), dir("3", vec![])]),
+                dir("node3", vec![dir("not_a_vdisk", vec![])]),
+            ],
+        );
+
+        assert_eq!(
+            Alien::from_dir(&alien_dir)
+                .into_iter()
+                .map(|alien| (alien.remote_node, alien.vdisk_id))
+                .collect::<Vec<_>>(),
+            vec![("node2".to_string(), 0), ("node2".to_string(), 3)]
+        );
+    }
+
+    #[test]
+    fn disk_status_serialization() {
+        let disk = Disk::from(dto::DiskState {
+            name: "disk1".to_string(),
+            path: "/bob/d1".to_string(),
+            is_active: false,
+        });
+
+        assert_eq!(
+            serde_json::to_value(disk).ok(),
+            Some(serde_json::json!({
+                "name": "disk1",
+                "path": "/bob/d1",
+                "status": "inactive"
+            }))
+        );
+    }
+}
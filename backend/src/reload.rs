@@ -0,0 +1,481 @@
+//! Hot reload of the configuration
+//!
+//! The configuration is reloaded once its file is modified or the process receives SIGHUP.
+//! Log filters, CORS policy, authentication, request timeout and the cluster are applied at runtime:
+//! requests to the nodes take the current timeout, and the users logged into a replaced cluster
+//! have to log in again. Changes of the other fields are reported as they take effect after a restart only
+
+use crate::{
+    config::{env_filter, ConfigExt, LoggerExt},
+    prelude::*,
+    services::logging::{LogFilters, FILE_OUTPUT, STDOUT_OUTPUT},
+};
+use axum::{
+    extract::State,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use cli::{AuthConfig, ClusterConfig, Config, ConfigArgs, FieldChange, LoggerConfig};
+use std::{
+    convert::Infallible,
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard},
+    time::{Duration, SystemTime},
+};
+use thiserror::Error;
+use tokio::{task::JoinHandle, time::interval};
+use tower::{Layer, ServiceExt};
+use tower_http::cors::CorsLayer;
+
+/// Fields applied without a restart, along with their nested fields
+pub const RUNTIME_FIELDS: &[&str] = &[
+    "logger.filter",
+    "logger.file.filter",
+    "logger.stdout.filter",
+    "cors",
+    "auth",
+    "request-timeout",
+    "cluster",
+];
+
+/// How often the configuration file is checked for modifications
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Error)]
+pub enum ReloadError {
+    #[error("Couldn't load the configuration")]
+    Load,
+    #[error("The configuration is invalid")]
+    Invalid,
+}
+
+/// Configuration fields that can change at runtime, shared with the services
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig {
+    inner: Arc<RwLock<Runtime>>,
+}
+
+#[derive(Debug)]
+struct Runtime {
+    cors: CorsLayer,
+    auth: AuthConfig,
+    request_timeout: Duration,
+    cluster: Option<Arc<ClusterConfig>>,
+}
+
+impl RuntimeConfig {
+    #[must_use]
+    pub fn new(config: &Config) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(Runtime::new(config))),
+        }
+    }
+
+    /// Time given to a Bob node to respond
+    #[must_use]
+    pub fn request_timeout(&self) -> Duration {
+        self.read().request_timeout
+    }
+
+    /// Configured cluster, replaced as a whole once it changes
+    #[must_use]
+    pub fn cluster(&self) -> Option<Arc<ClusterConfig>> {
+        self.read().cluster.clone()
+    }
+
+    /// Whether users may log into the nodes they specify, see [`AuthConfig::allow_hostname`]
+    #[must_use]
+    pub fn allow_hostname(&self) -> bool {
+        self.read().auth.allow_hostname
+    }
+
+    /// Whether the user with the `login` may use the admin endpoints, see [`AuthConfig::admins`]
+    #[must_use]
+    pub fn is_admin(&self, login: &str) -> bool {
+        self.read().auth.admins.iter().any(|admin| admin == login)
+    }
+
+    /// Current CORS policy
+    pub fn cors(&self) -> CorsLayer {
+        self.read().cors.clone()
+    }
+
+    /// Takes the runtime fields from the `config`
+    pub fn update(&self, config: &Config) {
+        let runtime = Runtime::new(config);
+        let mut current = self.inner.write().unwrap_or_else(PoisonError::into_inner);
+        current.cors = runtime.cors;
+        current.auth = runtime.auth;
+        current.request_timeout = runtime.request_timeout;
+        // The cluster is kept as is unless it changes, so that its users don't reconnect
+        if current.cluster != runtime.cluster {
+            current.cluster = runtime.cluster;
+        }
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Runtime> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Runtime {
+    fn new(config: &Config) -> Self {
+        Self {
+            cors: config.get_cors_configuration(),
+            auth: config.auth.clone(),
+            request_timeout: config.request_timeout,
+            cluster: config.cluster.clone().map(Arc::new),
+        }
+    }
+}
+
+/// Applies the current [`CORS`](CorsLayer) policy to the request
+pub async fn cors<B: Send + 'static>(
+    State(runtime): State<RuntimeConfig>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let mut next = Some(next);
+    let service = tower::service_fn(move |request| {
+        let next = next.take();
+        async move {
+            Ok::<_, Infallible>(match next {
+                Some(next) => next.run(request).await,
+                None => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            })
+        }
+    });
+
+    match runtime.cors().layer(service).oneshot(request).await {
+        Ok(response) => response,
+        Err(err) => match err {},
+    }
+}
+
+/// Reloads the configuration from the sources specified in `args` whenever the configuration file
+/// is modified or the process receives SIGHUP, until the task is aborted
+///
+/// # Panics
+///
+/// Panics if the signal handler couldn't be installed
+#[must_use]
+#[allow(clippy::expect_used)]
+pub fn spawn_reloader(
+    args: ConfigArgs,
+    config: Config,
+    runtime: RuntimeConfig,
+    log_filters: LogFilters,
+) -> JoinHandle<()> {
+    let mut reloader = Reloader::new(args, config, runtime, log_filters);
+    let mut ticker = interval(WATCH_INTERVAL);
+    #[cfg(unix)]
+    let mut hangups = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+        .expect("Couldn't install SIGHUP handler");
+
+    tokio::spawn(async move {
+        loop {
+            #[cfg(unix)]
+            let hangup = hangups.recv();
+            #[cfg(not(unix))]
+            let hangup = std::future::pending::<Option<()>>();
+
+            tokio::select! {
+                _ = ticker.tick() => if !reloader.modified() {
+                    continue;
+                },
+                _ = hangup => tracing::info!("SIGHUP received"),
+            }
+            if let Err(err) = reloader.reload() {
+                tracing::error!("Keeping the old configuration: {err:?}");
+            }
+        }
+    })
+}
+
+/// Applies the changes of the configuration
+struct Reloader {
+    args: ConfigArgs,
+    /// Configuration the server runs with
+    config: Config,
+    runtime: RuntimeConfig,
+    log_filters: LogFilters,
+    /// Modification time of the configuration file
+    modified: Option<SystemTime>,
+}
+
+impl Reloader {
+    fn new(
+        args: ConfigArgs,
+        config: Config,
+        runtime: RuntimeConfig,
+        log_filters: LogFilters,
+    ) -> Self {
+        let mut reloader = Self {
+            args,
+            config,
+            runtime,
+            log_filters,
+            modified: None,
+        };
+        reloader.modified();
+
+        reloader
+    }
+
+    /// Checks whether the configuration file was modified since the last check
+    fn modified(&mut self) -> bool {
+        let modified = self.args.config_file().and_then(|file| {
+            std::fs::metadata(file)
+                .and_then(|file| file.modified())
+                .ok()
+        });
+        let changed = modified.is_some() && modified != self.modified;
+        self.modified = modified;
+
+        changed
+    }
+
+    /// Loads the configuration and applies its runtime fields.
+    /// Returns the changes, which are logged as well
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the new configuration couldn't be loaded or is invalid,
+    /// the old one is kept then
+    fn reload(&mut self) -> Result<Vec<FieldChange>, ReloadError> {
+        let config = self.args.load().change_context(ReloadError::Load)?;
+        let changes = self.config.diff(&config);
+        if let Err(err) = validate(&config) {
+            for change in &changes {
+                tracing::warn!("Rejected change of {change}");
+            }
+            return Err(err);
+        }
+        if changes.is_empty() {
+            tracing::info!("Configuration reloaded, nothing changed");
+            return Ok(changes);
+        }
+
+        for change in &changes {
+            if change.field == "cluster" || change.field.starts_with("cluster.") {
+                tracing::info!(
+                    "Applied change of {change}, the logged in users have to log in again"
+                );
+            } else if is_runtime(&change.field) {
+                tracing::info!("Applied change of {change}");
+            } else {
+                tracing::warn!("Change of {change} takes effect after a restart");
+            }
+        }
+        self.runtime.update(&config);
+        self.apply_log_filters(&config.logger);
+        self.config = with_runtime_fields(self.config.clone(), config);
+
+        Ok(changes)
+    }
+
+    fn apply_log_filters(&self, logger: &LoggerConfig) {
+        if let Some(file) = &logger.file {
+            self.log_filters
+                .configure(FILE_OUTPUT, logger.directives(file.filter.as_deref()));
+        }
+        if let Some(stdout) = &logger.stdout {
+            self.log_filters
+                .configure(STDOUT_OUTPUT, logger.directives(stdout.filter.as_deref()));
+        }
+    }
+}
+
+/// Checks the configuration along with the directives of the log filters
+fn validate(config: &Config) -> Result<(), ReloadError> {
+    config.validate().change_context(ReloadError::Invalid)?;
+    let logger = &config.logger;
+    let filters = [
+        logger.file.as_ref().map(|file| file.filter.as_deref()),
+        logger
+            .stdout
+            .as_ref()
+            .map(|stdout| stdout.filter.as_deref()),
+    ];
+    for filter in filters.into_iter().flatten() {
+        env_filter(&logger.directives(filter)).change_context(ReloadError::Invalid)?;
+    }
+
+    Ok(())
+}
+
+/// Whether the change of the `field` is applied at runtime
+fn is_runtime(field: &str) -> bool {
+    RUNTIME_FIELDS.iter().any(|runtime| {
+        field
+            .strip_prefix(runtime)
+            .is_some_and(|nested| nested.is_empty() || nested.starts_with('.'))
+    })
+}
+
+/// The `running` configuration with the [runtime fields](RUNTIME_FIELDS) taken from the `new` one
+fn with_runtime_fields(mut running: Config, new: Config) -> Config {
+    running.logger.filter = new.logger.filter;
+    if let (Some(running), Some(new)) = (&mut running.logger.file, new.logger.file) {
+        running.filter = new.filter;
+    }
+    if let (Some(running), Some(new)) = (&mut running.logger.stdout, new.logger.stdout) {
+        running.filter = new.filter;
+    }
+    running.cors = new.cors;
+    running.auth = new.auth;
+    running.request_timeout = new.request_timeout;
+    running.cluster = new.cluster;
+
+    running
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use axum::{middleware::from_fn_with_state, routing::get, Router};
+    use cli::{Command, CorsConfig, Parser};
+    use hyper::{
+        header::{ACCESS_CONTROL_ALLOW_ORIGIN, ORIGIN},
+        Body,
+    };
+    use std::path::Path;
+    use tracing_subscriber::{fmt, prelude::*, reload, EnvFilter};
+
+    fn args(file: &Path) -> ConfigArgs {
+        let args = cli::Args::parse_from(["bob-management", "serve", "-c", file.to_str().unwrap()]);
+        match args.command {
+            Command::Serve(args) => args,
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn runtime_cors() {
+        let runtime = RuntimeConfig::new(&Config::default());
+        let router = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(from_fn_with_state(runtime.clone(), cors));
+        let request = || {
+            Request::get("/")
+                .header(ORIGIN, "http://localhost:3000")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = router.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+
+        runtime.update(&Config {
+            cors: CorsConfig {
+                allow_all: true,
+                ..CorsConfig::default()
+            },
+            ..Config::default()
+        });
+        let response = router.oneshot(request()).await.unwrap();
+        assert_eq!(
+            response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "http://localhost:3000"
+        );
+    }
+
+    #[tokio::test]
+    async fn reload_config() {
+        let file =
+            std::env::temp_dir().join(format!("bob-management-reload-{}.yaml", std::process::id()));
+        let write = |yaml: &str| std::fs::write(&file, yaml).unwrap();
+        write("{address: 0.0.0.0:9000, logger: {stdout: {enabled: true}}}");
+        let args = args(&file);
+        let config = args.load().unwrap();
+        let runtime = RuntimeConfig::new(&config);
+
+        let log_filters = LogFilters::default();
+        let (filter, handle) = reload::Layer::new(EnvFilter::new("info"));
+        log_filters.add(STDOUT_OUTPUT, "info".to_string(), handle);
+        let _subscriber = tracing_subscriber::registry().with(fmt::layer().with_filter(filter));
+
+        let mut reloader = Reloader::new(args, config, runtime.clone(), log_filters.clone());
+        assert!(!reloader.modified());
+
+        write(
+            "
+address: 0.0.0.0:9100
+request-timeout: 1s
+logger: {filter: debug, stdout: {enabled: true, filter: debug}}
+cluster: {nodes: [{address: 'node1:8000'}]}
+",
+        );
+        let changes: Vec<_> = reloader
+            .reload()
+            .unwrap()
+            .into_iter()
+            .map(|change| change.field)
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                "address",
+                "request-timeout",
+                "logger.stdout.filter",
+                "logger.filter",
+                "cluster"
+            ]
+        );
+        assert_eq!(runtime.request_timeout(), Duration::from_secs(1));
+        assert_eq!(runtime.cluster().unwrap().nodes[0].address, "node1:8000");
+        assert_eq!(log_filters.get()[0].directives, "debug");
+        // Restart is still needed for the address to change
+        assert_eq!(reloader.config.address.port(), 9000);
+
+        let cluster = runtime.cluster().unwrap();
+        write("{address: 0.0.0.0:9100, request-timeout: 2s, cluster: {nodes: [{address: 'node1:8000'}]}}");
+        reloader.reload().unwrap();
+        assert!(Arc::ptr_eq(&cluster, &runtime.cluster().unwrap()));
+
+        for invalid in [
+            "{address: 0.0.0.0:9100, cluster: {nodes: []}}",
+            "{address: 0.0.0.0:9100, logger: {stdout: {enabled: true, filter: 'bob=loud'}}}",
+        ] {
+            write(invalid);
+            assert!(matches!(
+                reloader.reload().unwrap_err().current_context(),
+                ReloadError::Invalid
+            ));
+            assert_eq!(runtime.request_timeout(), Duration::from_secs(2));
+            assert!(runtime.cluster().is_some());
+        }
+        write("address: [");
+        assert!(matches!(
+            reloader.reload().unwrap_err().current_context(),
+            ReloadError::Load
+        ));
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn runtime_fields() {
+        for field in [
+            "cluster",
+            "cluster.nodes",
+            "logger.stdout.filter",
+            "request-timeout",
+        ] {
+            assert!(is_runtime(field), "{field}");
+        }
+        for field in [
+            "address",
+            "logger.stdout",
+            "logger.stdout.format",
+            "cluster-name",
+            "tls",
+        ] {
+            assert!(!is_runtime(field), "{field}");
+        }
+    }
+}
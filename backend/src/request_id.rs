@@ -0,0 +1,172 @@
+//! Identification and access logging of the API requests
+//!
+//! Every request is processed within a `request` span, which id is passed on to the Bob nodes
+
+use axum::{
+    extract::MatchedPath,
+    http::{HeaderName, HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use rand::Rng;
+use std::{fmt::Write, future::Future, time::Instant};
+use tracing::{field::Empty, Instrument};
+
+/// Header with the id of the request
+pub static X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest id accepted from the clients, longer ones are replaced with generated ids
+const MAX_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Id of the request being processed, if any
+#[must_use]
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Runs `future` as a part of the request with the `id`
+pub async fn scope<F: Future>(id: String, future: F) -> F::Output {
+    REQUEST_ID.scope(id, future).await
+}
+
+/// Processes the request within the scope of its id,
+/// taken from the `X-Request-Id` header or generated if there is none
+///
+/// The request is logged on completion within the span with its method, route template,
+/// status and latency. The id is returned in the response's `X-Request-Id` header
+pub async fn request_id<B>(request: Request<B>, next: Next<B>) -> Response {
+    let id = request
+        .headers()
+        .get(&X_REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= MAX_ID_LENGTH)
+        .map_or_else(generate, ToString::to_string);
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| request.uri().path(), MatchedPath::as_str)
+        .to_string();
+    let span = tracing::info_span!(
+        "request",
+        id = %id,
+        method = %request.method(),
+        route = %route,
+        status = Empty,
+        latency_ms = Empty,
+    );
+
+    let start = Instant::now();
+    let mut response = scope(id.clone(), next.run(request))
+        .instrument(span.clone())
+        .await;
+    let latency = start.elapsed();
+
+    span.record("status", response.status().as_u16());
+    span.record("latency_ms", latency.as_millis());
+    span.in_scope(|| tracing::info!("{} in {latency:?}", response.status()));
+    if let Ok(id) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(X_REQUEST_ID.clone(), id);
+    }
+
+    response
+}
+
+/// Random 128-bit id in hex
+fn generate() -> String {
+    let bytes: [u8; 16] = rand::thread_rng().gen();
+    bytes
+        .iter()
+        .fold(String::with_capacity(32), |mut id, byte| {
+            let _ = write!(id, "{byte:02x}");
+            id
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::{error::ProblemDetails, services::api_not_found};
+    use axum::{middleware::from_fn, routing::get, Router};
+    use hyper::{body::to_bytes, Body};
+    use std::sync::{Arc, Mutex};
+    use tower::ServiceExt;
+
+    /// Log lines written by the subscriber
+    #[derive(Clone, Default)]
+    struct Logs(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn not_found(request: Request<Body>) -> ProblemDetails {
+        let response = Router::new()
+            .fallback(api_not_found)
+            .layer(from_fn(request_id))
+            .oneshot(request)
+            .await
+            .unwrap();
+        serde_json::from_slice(&to_bytes(response.into_body()).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn request_id_scope() {
+        let request = Request::get("/api").header(&X_REQUEST_ID, "req-1");
+        let problem = not_found(request.body(Body::empty()).unwrap()).await;
+        assert_eq!(problem.request_id.as_deref(), Some("req-1"));
+
+        let problem = not_found(Request::get("/api").body(Body::empty()).unwrap()).await;
+        assert_eq!(problem.request_id.unwrap().len(), 32);
+        assert_eq!(current(), None);
+    }
+
+    #[tokio::test]
+    async fn access_log() {
+        let logs = Logs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let response = Router::new()
+            .nest(
+                "/api/v1",
+                Router::new().route("/nodes/:node_name", get(|| async { "node" })),
+            )
+            .layer(from_fn(request_id))
+            .oneshot(
+                Request::get("/api/v1/nodes/node1")
+                    .header(&X_REQUEST_ID, "req-2")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[&X_REQUEST_ID], "req-2");
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        for field in [
+            "id=req-2",
+            "method=GET",
+            "route=/api/v1/nodes/:node_name",
+            "status=200",
+            "latency_ms=",
+        ] {
+            assert!(logs.contains(field), "{field} is missing in {logs}");
+        }
+    }
+}
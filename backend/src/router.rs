@@ -175,9 +175,8 @@ where
         &path
             .split('/')
             .map(|arg| {
-                arg.starts_with(':')
-                    .then(|| ["{", &arg[1..], "}"].concat())
-                    .unwrap_or_else(|| arg.to_string())
+                arg.strip_prefix(':')
+                    .map_or_else(|| arg.to_string(), |arg| ["{", arg, "}"].concat())
             })
             .collect::<Vec<_>>()
             .join("/"),
@@ -0,0 +1,298 @@
+use super::APIError;
+use crate::{
+    connector::{BobClient, ClusterClient},
+    models::api::{Alien, AliensSummary, NodeAliens, NodeStatus, RemoteNodeAliens},
+    prelude::*,
+};
+use axum::{extract::Path, Extension, Json};
+use futures::future::join_all;
+use hyper::StatusCode;
+use std::collections::BTreeMap;
+
+/// Returns aliens stored on the node: remote nodes and vdisks they belong to
+///
+/// # Errors
+///
+/// This function will return an error if the node is not a part of the cluster
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        get,
+        context_path = "/api/v1",
+        path = "/nodes/{node_name}/aliens",
+        params(
+            ("node_name" = String, Path, description = "Name of the node")
+        ),
+        responses(
+            (status = 200, body = NodeAliens, content_type = "application/json", description = "Node's aliens"),
+            (status = 404, body = ProblemDetails, content_type = "application/problem+json", description = "Node not found")
+        )
+    ))]
+pub async fn get_node_aliens(
+    Extension(cluster): Extension<ClusterClient>,
+    Path(node_name): Path<String>,
+) -> AxumResult<Json<NodeAliens>> {
+    let client = cluster.node(&node_name).ok_or(APIError::NotFound)?;
+
+    Ok(Json(fetch_aliens(&node_name, client).await))
+}
+
+/// Returns the amount of alien data waiting to be pushed back, across the cluster
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        get,
+        context_path = "/api/v1",
+        path = "/aliens",
+        responses(
+            (status = 200, body = AliensSummary, content_type = "application/json", description = "Pending aliens summary"),
+        )
+    ))]
+pub async fn get_aliens_summary(
+    Extension(cluster): Extension<ClusterClient>,
+) -> AxumResult<Json<AliensSummary>> {
+    let nodes = join_all(
+        cluster
+            .nodes()
+            .map(|(name, client)| fetch_aliens(name, client)),
+    )
+    .await;
+
+    let mut summary = AliensSummary::default();
+    let mut remote_nodes = BTreeMap::new();
+    for node in nodes {
+        if node.status != NodeStatus::Online {
+            summary.unavailable_nodes.push(node.node);
+            continue;
+        }
+        for alien in node.aliens {
+            let unsized_count = u64::from(alien.size.is_none());
+            let size = alien.size.unwrap_or_default();
+            summary.vdisks_count += 1;
+            summary.size += size;
+            summary.unsized_vdisks_count += unsized_count;
+            let remote = remote_nodes
+                .entry(alien.remote_node.clone())
+                .or_insert_with(|| RemoteNodeAliens {
+                    remote_node: alien.remote_node,
+                    vdisks_count: 0,
+                    size: 0,
+                    unsized_vdisks_count: 0,
+                });
+            remote.vdisks_count += 1;
+            remote.size += size;
+            remote.unsized_vdisks_count += unsized_count;
+        }
+    }
+    summary.remote_nodes = remote_nodes.into_values().collect();
+
+    Ok(Json(summary))
+}
+
+/// Starts pushing the node's aliens back to the nodes they belong to
+///
+/// # Errors
+///
+/// This function will return an error if the node doesn't exist
+/// or the node rejected the request
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        post,
+        context_path = "/api/v1",
+        path = "/nodes/{node_name}/aliens/sync",
+        params(
+            ("node_name" = String, Path, description = "Name of the node")
+        ),
+        responses(
+            (status = 200, description = "Aliens synchronization started"),
+            (status = 403, body = ProblemDetails, content_type = "application/problem+json", description = "Not enough permissions"),
+            (status = 404, body = ProblemDetails, content_type = "application/problem+json", description = "Node not found"),
+            (status = 502, body = ProblemDetails, content_type = "application/problem+json", description = "Node failed to start the synchronization"),
+            (status = 503, body = ProblemDetails, content_type = "application/problem+json", description = "Node couldn't be reached"),
+            (status = 504, body = ProblemDetails, content_type = "application/problem+json", description = "Node didn't respond in time")
+        )
+    ))]
+pub async fn sync_aliens(
+    Extension(cluster): Extension<ClusterClient>,
+    Path(node_name): Path<String>,
+) -> AxumResult<StatusCode> {
+    let client = cluster.node(&node_name).ok_or(APIError::NotFound)?;
+    client
+        .sync_aliens()
+        .await
+        .map_err(|err| APIError::node_problem(&node_name, err))?;
+    tracing::info!("aliens synchronization started on {node_name}");
+
+    Ok(StatusCode::OK)
+}
+
+/// Detaches the node's alien partitions
+///
+/// # Errors
+///
+/// This function will return an error if the node doesn't exist
+/// or the node rejected the request
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        post,
+        context_path = "/api/v1",
+        path = "/nodes/{node_name}/aliens/detach",
+        params(
+            ("node_name" = String, Path, description = "Name of the node")
+        ),
+        responses(
+            (status = 200, description = "Aliens detached"),
+            (status = 403, body = ProblemDetails, content_type = "application/problem+json", description = "Not enough permissions"),
+            (status = 404, body = ProblemDetails, content_type = "application/problem+json", description = "Node not found"),
+            (status = 502, body = ProblemDetails, content_type = "application/problem+json", description = "Node failed to detach the aliens"),
+            (status = 503, body = ProblemDetails, content_type = "application/problem+json", description = "Node couldn't be reached"),
+            (status = 504, body = ProblemDetails, content_type = "application/problem+json", description = "Node didn't respond in time")
+        )
+    ))]
+pub async fn detach_aliens(
+    Extension(cluster): Extension<ClusterClient>,
+    Path(node_name): Path<String>,
+) -> AxumResult<StatusCode> {
+    let client = cluster.node(&node_name).ok_or(APIError::NotFound)?;
+    client
+        .detach_aliens()
+        .await
+        .map_err(|err| APIError::node_problem(&node_name, err))?;
+    tracing::info!("aliens detached on {node_name}");
+
+    Ok(StatusCode::OK)
+}
+
+/// Reads aliens of the node from its alien directory, the node is offline if it couldn't be read
+/// or timed out if it didn't respond in time
+async fn fetch_aliens(name: &str, client: &BobClient) -> NodeAliens {
+    let dir = client.get_alien_dir().await;
+    let status = NodeStatus::from_result(&dir);
+    let aliens = dir
+        .map_err(|err| tracing::warn!("node {name}: {err:?}"))
+        .map(|dir| Alien::from_dir(&dir))
+        .unwrap_or_default();
+
+    NodeAliens {
+        node: name.to_string(),
+        status,
+        aliens,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::connector::{
+        dto,
+        mock::{client, offline_address, send, MockNode},
+    };
+    use axum::{
+        routing::{get, post},
+        Router,
+    };
+    use hyper::Method;
+
+    fn dir(path: &str, size: Option<u64>, children: Vec<dto::Dir>) -> dto::Dir {
+        dto::Dir {
+            name: path.rsplit('/').next().unwrap().to_string(),
+            path: path.to_string(),
+            size,
+            children,
+        }
+    }
+
+    /// `node1` and `node2` keep aliens of `node3`, which is offline
+    fn app() -> Router {
+        let mut node1 = MockNode::new("node1", &[0]);
+        node1.alien_dir = dir(
+            "/a",
+            None,
+            vec![dir(
+                "/a/node3",
+                None,
+                vec![
+                    dir("/a/node3/0", Some(10), vec![]),
+                    dir("/a/node3/1", Some(5), vec![]),
+                ],
+            )],
+        );
+        let mut node2 = MockNode::new("node2", &[0]);
+        node2.alien_dir = dir(
+            "/a",
+            None,
+            vec![dir("/a/node3", None, vec![dir("/a/node3/0", None, vec![])])],
+        );
+        let node1 = client(&node1.spawn().to_string());
+        let cluster = ClusterClient::new(
+            node1.clone(),
+            [
+                ("node1".to_string(), node1),
+                ("node2".to_string(), client(&node2.spawn().to_string())),
+                ("node3".to_string(), client(&offline_address())),
+            ],
+        );
+
+        Router::new()
+            .route("/aliens", get(get_aliens_summary))
+            .route("/nodes/:node_name/aliens", get(get_node_aliens))
+            .route("/nodes/:node_name/aliens/sync", post(sync_aliens))
+            .route("/nodes/:node_name/aliens/detach", post(detach_aliens))
+            .layer(Extension(cluster))
+    }
+
+    #[tokio::test]
+    async fn node_aliens() {
+        let app = app();
+
+        let (status, body) = send(&app, Method::GET, "/nodes/node1/aliens").await;
+        assert_eq!(status, StatusCode::OK);
+        let aliens: NodeAliens = serde_json::from_slice(&body).unwrap();
+        assert_eq!(aliens.status, NodeStatus::Online);
+        assert_eq!(
+            aliens
+                .aliens
+                .iter()
+                .map(|alien| (alien.remote_node.as_str(), alien.vdisk_id, alien.size))
+                .collect::<Vec<_>>(),
+            vec![("node3", 0, Some(10)), ("node3", 1, Some(5))]
+        );
+
+        let (_, body) = send(&app, Method::GET, "/nodes/node3/aliens").await;
+        let aliens: NodeAliens = serde_json::from_slice(&body).unwrap();
+        assert_eq!(aliens.status, NodeStatus::Offline);
+
+        let (status, _) = send(&app, Method::GET, "/nodes/node4/aliens").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn aliens_summary() {
+        let (status, body) = send(&app(), Method::GET, "/aliens").await;
+        assert_eq!(status, StatusCode::OK);
+        let summary: AliensSummary = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(
+            summary,
+            AliensSummary {
+                vdisks_count: 3,
+                size: 15,
+                unsized_vdisks_count: 1,
+                remote_nodes: vec![RemoteNodeAliens {
+                    remote_node: "node3".to_string(),
+                    vdisks_count: 3,
+                    size: 15,
+                    unsized_vdisks_count: 1,
+                }],
+                unavailable_nodes: vec!["node3".to_string()],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn aliens_operations() {
+        let app = app();
+
+        let (status, _) = send(&app, Method::POST, "/nodes/node1/aliens/sync").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, Method::POST, "/nodes/node1/aliens/detach").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, Method::POST, "/nodes/node3/aliens/sync").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
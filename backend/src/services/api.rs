@@ -0,0 +1,403 @@
+use super::APIError;
+use crate::{
+    connector::{dto, BobClient, ClusterClient},
+    models::api::{
+        Alien, ClusterInfo, ClusterSpace, Disk, NodeDetails, NodeStatus, NodeVersion, SpaceInfo,
+        VDisk, VDiskStatus, VDisksCount,
+    },
+    prelude::*,
+};
+use axum::{extract::Path, Extension, Json};
+use futures::{future::join_all, stream::FuturesUnordered, StreamExt};
+use std::collections::HashSet;
+
+/// State of a single node, gathered at once.
+/// Requests to the node that failed are left empty
+#[derive(Debug, Default)]
+pub struct NodeState {
+    pub name: String,
+    /// Whether the node's status request timed out
+    pub timed_out: bool,
+    pub status: Option<dto::Node>,
+    pub disks: Option<Vec<dto::DiskState>>,
+    pub space: Option<dto::SpaceInfo>,
+    pub version: Option<dto::VersionInfo>,
+}
+
+impl NodeState {
+    /// Fetches status, disks, space and version of the node concurrently
+    pub async fn fetch(name: &str, client: &BobClient) -> Self {
+        let (status, disks, space, version) = tokio::join!(
+            client.get_status(),
+            client.get_disks(),
+            client.get_space(),
+            client.get_version()
+        );
+
+        Self {
+            name: name.to_string(),
+            timed_out: NodeStatus::from_result(&status) == NodeStatus::Timeout,
+            status: log_failure(name, status),
+            disks: log_failure(name, disks),
+            space: log_failure(name, space),
+            version: log_failure(name, version),
+        }
+    }
+
+    /// Fetches state of every node of the cluster concurrently
+    pub async fn fetch_all(cluster: &ClusterClient) -> Vec<Self> {
+        join_all(
+            cluster
+                .nodes()
+                .map(|(name, client)| Self::fetch(name, client)),
+        )
+        .await
+    }
+
+    #[must_use]
+    pub const fn is_online(&self) -> bool {
+        self.status.is_some()
+    }
+
+    #[must_use]
+    pub const fn node_status(&self) -> NodeStatus {
+        if self.is_online() {
+            NodeStatus::Online
+        } else if self.timed_out {
+            NodeStatus::Timeout
+        } else {
+            NodeStatus::Offline
+        }
+    }
+
+    #[must_use]
+    pub fn node_version(&self) -> NodeVersion {
+        NodeVersion {
+            node: self.name.clone(),
+            bob_version: self
+                .version
+                .as_ref()
+                .map(|version| version.bob_version.version.clone()),
+            pearl_version: self
+                .version
+                .as_ref()
+                .map(|version| version.pearl_version.version.clone()),
+        }
+    }
+}
+
+/// Returns the whole-cluster overview
+///
+/// # Errors
+///
+/// This function will return an error if none of the nodes could list the cluster's vdisks
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        get,
+        context_path = "/api/v1",
+        path = "/cluster",
+        responses(
+            (status = 200, body = ClusterInfo, content_type = "application/json", description = "Cluster overview"),
+            (status = 503, body = ProblemDetails, content_type = "application/problem+json", description = "Cluster couldn't be reached")
+        )
+    ))]
+pub async fn get_cluster(
+    Extension(cluster): Extension<ClusterClient>,
+) -> AxumResult<Json<ClusterInfo>> {
+    let nodes = NodeState::fetch_all(&cluster).await;
+    let vdisks = get_vdisks(&cluster, &nodes)
+        .await
+        .ok_or(APIError::NodeUnavailable)?;
+
+    let mut info = ClusterInfo {
+        nodes_count: nodes.len() as u64,
+        nodes_online: nodes.iter().filter(|node| node.is_online()).count() as u64,
+        nodes_timed_out: nodes
+            .iter()
+            .filter(|node| node.timed_out)
+            .map(|node| node.name.clone())
+            .collect(),
+        vdisks: VDisksCount::default(),
+        space: ClusterSpace::default(),
+        versions: Vec::with_capacity(nodes.len()),
+    };
+    let available = available_disks(&nodes);
+    for vdisk in &vdisks {
+        info.vdisks.add(vdisk_status(vdisk, &available));
+    }
+    for node in &nodes {
+        if let Some(space) = &node.space {
+            info.space += &SpaceInfo::from(space.clone());
+        }
+        info.versions.push(node.node_version());
+    }
+
+    Ok(Json(info))
+}
+
+/// Returns the detailed state of the node
+///
+/// # Errors
+///
+/// This function will return an error if the node is not a part of the cluster
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        get,
+        context_path = "/api/v1",
+        path = "/nodes/{node_name}",
+        params(
+            ("node_name" = String, Path, description = "Name of the node")
+        ),
+        responses(
+            (status = 200, body = NodeDetails, content_type = "application/json", description = "Node's state"),
+            (status = 404, body = ProblemDetails, content_type = "application/problem+json", description = "Node not found")
+        )
+    ))]
+pub async fn get_node(
+    Extension(cluster): Extension<ClusterClient>,
+    Path(node_name): Path<String>,
+) -> AxumResult<Json<NodeDetails>> {
+    let client = cluster.node(&node_name).ok_or(APIError::NotFound)?;
+    let (node, alien_dir) =
+        tokio::join!(NodeState::fetch(&node_name, client), client.get_alien_dir());
+    let version = node.version.is_some().then(|| node.node_version());
+
+    Ok(Json(NodeDetails {
+        status: node.node_status(),
+        address: node.status.as_ref().map(|status| status.address.clone()),
+        vdisks: node
+            .status
+            .map(|status| status.vdisks.into_iter().map(VDisk::from).collect())
+            .unwrap_or_default(),
+        disks: node
+            .disks
+            .map(|disks| disks.into_iter().map(Disk::from).collect())
+            .unwrap_or_default(),
+        aliens: log_failure(&node_name, alien_dir)
+            .map(|dir| Alien::from_dir(&dir))
+            .unwrap_or_default(),
+        space: node.space.map(SpaceInfo::from),
+        version,
+        name: node_name,
+    }))
+}
+
+/// Lists vdisks of the cluster, requesting every online node at once
+/// and taking the first one that succeeds
+async fn get_vdisks(cluster: &ClusterClient, nodes: &[NodeState]) -> Option<Vec<dto::VDisk>> {
+    let mut requests: FuturesUnordered<_> = nodes
+        .iter()
+        .filter(|node| node.is_online())
+        .filter_map(|node| {
+            let client = cluster.node(&node.name)?;
+            Some(async move { (node.name.as_str(), client.get_vdisks().await) })
+        })
+        .collect();
+    while let Some((name, vdisks)) = requests.next().await {
+        if let Some(vdisks) = log_failure(name, vdisks) {
+            return Some(vdisks);
+        }
+    }
+
+    None
+}
+
+/// Returns `(node, disk)` pairs of the active disks of the online nodes
+#[must_use]
+pub fn available_disks(nodes: &[NodeState]) -> HashSet<(&str, &str)> {
+    nodes
+        .iter()
+        .filter(|node| node.is_online())
+        .flat_map(|node| {
+            node.disks.iter().flatten().filter_map(|disk| {
+                disk.is_active
+                    .then_some((node.name.as_str(), disk.name.as_str()))
+            })
+        })
+        .collect()
+}
+
+/// Health of the vdisk given the `(node, disk)` pairs that are available
+#[must_use]
+#[allow(clippy::implicit_hasher)]
+pub fn vdisk_status(vdisk: &dto::VDisk, available: &HashSet<(&str, &str)>) -> VDiskStatus {
+    let good = vdisk
+        .replicas
+        .iter()
+        .filter(|replica| available.contains(&(replica.node.as_str(), replica.disk.as_str())))
+        .count();
+
+    if good == vdisk.replicas.len() {
+        VDiskStatus::Good
+    } else if good == 0 {
+        VDiskStatus::Offline
+    } else {
+        VDiskStatus::Degraded
+    }
+}
+
+fn log_failure<T>(node: &str, result: Result<T, crate::connector::ClientError>) -> Option<T> {
+    result
+        .map_err(|err| tracing::warn!("node {node}: {err:?}"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::connector::mock::{client, offline_address, replica, spawn_hanging_node, MockNode};
+    use crate::models::api::DiskStatus;
+    use axum::{routing::get, Router};
+    use hyper::{body::to_bytes, Body, Request, StatusCode};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn cluster_overview() {
+        let mut node1 = MockNode::new("node1", &[0, 2]);
+        node1.status.vdisks[0]
+            .replicas
+            .push(replica("node2", "disk1", 0));
+        node1.status.vdisks.push(dto::VDisk {
+            id: 1,
+            replicas: vec![replica("node2", "disk1", 1)],
+        });
+        let node1 = client(&node1.spawn().to_string());
+        let cluster = ClusterClient::new(
+            node1.clone(),
+            [
+                ("node1".to_string(), node1),
+                ("node2".to_string(), client(&offline_address())),
+            ],
+        );
+
+        let response = Router::new()
+            .route("/cluster", get(get_cluster))
+            .layer(Extension(cluster))
+            .oneshot(Request::get("/cluster").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let info: ClusterInfo =
+            serde_json::from_slice(&to_bytes(response.into_body()).await.unwrap()).unwrap();
+
+        assert_eq!(info.nodes_count, 2);
+        assert_eq!(info.nodes_online, 1);
+        assert_eq!(
+            info.vdisks,
+            VDisksCount {
+                good: 1,
+                degraded: 1,
+                offline: 1
+            }
+        );
+        assert_eq!(info.space.total_disk, 100);
+        assert_eq!(info.versions[0].bob_version.as_deref(), Some("2.1.0"));
+        assert_eq!(info.versions[1].bob_version, None);
+    }
+
+    #[test]
+    fn inactive_disk_degrades_vdisk() {
+        let vdisk = dto::VDisk {
+            id: 0,
+            replicas: vec![replica("node1", "disk1", 0), replica("node1", "disk2", 0)],
+        };
+
+        assert_eq!(
+            vdisk_status(&vdisk, &HashSet::from([("node1", "disk1")])),
+            VDiskStatus::Degraded
+        );
+    }
+
+    #[tokio::test]
+    async fn node_details() {
+        let mut node1 = MockNode::new("node1", &[0]);
+        node1.disks.push(dto::DiskState {
+            name: "disk2".to_string(),
+            path: "/bob/d2".to_string(),
+            is_active: false,
+        });
+        let node1 = client(&node1.spawn().to_string());
+        let cluster = ClusterClient::new(
+            node1.clone(),
+            [
+                ("node1".to_string(), node1),
+                ("node2".to_string(), client(&offline_address())),
+            ],
+        );
+        let router = Router::new()
+            .route("/nodes/:node_name", get(get_node))
+            .layer(Extension(cluster));
+        let get_details = |uri: &'static str| {
+            let router = router.clone();
+            async move {
+                router
+                    .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let response = get_details("/nodes/node1").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let details: NodeDetails =
+            serde_json::from_slice(&to_bytes(response.into_body()).await.unwrap()).unwrap();
+        assert_eq!(details.status, NodeStatus::Online);
+        assert_eq!(
+            details
+                .disks
+                .iter()
+                .map(|disk| disk.status)
+                .collect::<Vec<_>>(),
+            vec![DiskStatus::Active, DiskStatus::Inactive]
+        );
+        assert_eq!(details.vdisks.len(), 1);
+        assert_eq!(details.space.unwrap().total_disk, 100);
+
+        let response = get_details("/nodes/node2").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let details: NodeDetails =
+            serde_json::from_slice(&to_bytes(response.into_body()).await.unwrap()).unwrap();
+        assert_eq!(details.status, NodeStatus::Offline);
+        assert!(details.disks.is_empty());
+
+        let response = get_details("/nodes/node3").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn timed_out_node() {
+        let node1 = client(&MockNode::new("node1", &[0]).spawn().to_string());
+        let node2 = BobClient::try_new(
+            &spawn_hanging_node().to_string(),
+            Duration::from_millis(100),
+        )
+        .unwrap();
+        let cluster = ClusterClient::new(
+            node1.clone(),
+            [("node1".to_string(), node1), ("node2".to_string(), node2)],
+        );
+        let router = Router::new()
+            .route("/cluster", get(get_cluster))
+            .route("/nodes/:node_name", get(get_node))
+            .layer(Extension(cluster));
+
+        let response = router
+            .clone()
+            .oneshot(Request::get("/cluster").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let info: ClusterInfo =
+            serde_json::from_slice(&to_bytes(response.into_body()).await.unwrap()).unwrap();
+        assert_eq!(info.nodes_online, 1);
+        assert_eq!(info.nodes_timed_out, vec!["node2".to_string()]);
+
+        let response = router
+            .oneshot(Request::get("/nodes/node2").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let details: NodeDetails =
+            serde_json::from_slice(&to_bytes(response.into_body()).await.unwrap()).unwrap();
+        assert_eq!(details.status, NodeStatus::Timeout);
+    }
+}
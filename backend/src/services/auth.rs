@@ -0,0 +1,673 @@
+use super::APIError;
+use crate::{
+    connector::{BobClient, ClientError, ClusterClient, Credentials},
+    models::api::LoginRequest,
+    prelude::*,
+    reload::RuntimeConfig,
+};
+use axum::{
+    http::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension, Json, Router,
+};
+use axum_login::{
+    axum_sessions::{async_session::MemoryStore as SessionStore, PersistencePolicy, SessionLayer},
+    extractors::AuthContext,
+    memory_store::MemoryStore,
+    secrecy::SecretVec,
+    AuthLayer, AuthUser,
+};
+use cli::ClusterConfig;
+use hyper::{Body, StatusCode};
+use rand::Rng;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::RwLock;
+
+/// Lifetime of the session, counted from the login
+pub const SESSION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// User logged into the Bob cluster
+#[derive(Clone, Debug)]
+pub struct BobUser {
+    id: u64,
+    login: String,
+    /// Random per-login secret, invalidates the session once the user is logged out
+    secret: Vec<u8>,
+    cluster: ClusterClient,
+    /// Configured cluster the user logged into, `None` for the node specified on login
+    cluster_config: Option<Arc<ClusterConfig>>,
+    /// End of the user's session, the user is forgotten after it
+    expires_at: Instant,
+}
+
+impl BobUser {
+    #[must_use]
+    pub fn login(&self) -> &str {
+        &self.login
+    }
+
+    /// Cluster client authorized with the user's credentials
+    #[must_use]
+    pub const fn cluster(&self) -> &ClusterClient {
+        &self.cluster
+    }
+}
+
+impl AuthUser<u64> for BobUser {
+    fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    fn get_password_hash(&self) -> SecretVec<u8> {
+        SecretVec::new(self.secret.clone())
+    }
+}
+
+pub type BobUsers = Arc<RwLock<HashMap<u64, BobUser>>>;
+
+pub type BobAuthContext = AuthContext<u64, BobUser, MemoryStore<u64, BobUser>>;
+
+/// Authentication state shared between the login handlers
+#[derive(Clone, Debug)]
+pub struct AuthState {
+    users: BobUsers,
+    sessions: SessionStore,
+    session_ttl: Duration,
+    runtime: RuntimeConfig,
+    /// Whether the session cookie is sent over HTTPS only
+    secure_cookie: bool,
+}
+
+impl AuthState {
+    /// Users log into the currently configured cluster,
+    /// or into the node they specify if that's [allowed](RuntimeConfig::allow_hostname)
+    #[must_use]
+    pub fn new(runtime: RuntimeConfig) -> Self {
+        Self {
+            users: BobUsers::default(),
+            sessions: SessionStore::new(),
+            session_ttl: SESSION_TTL,
+            runtime,
+            secure_cookie: false,
+        }
+    }
+
+    /// Overrides the [lifetime](SESSION_TTL) of the sessions
+    #[must_use]
+    pub const fn with_session_ttl(mut self, session_ttl: Duration) -> Self {
+        self.session_ttl = session_ttl;
+        self
+    }
+
+    /// Whether the `user` logged into the currently configured cluster,
+    /// or into a specified node while that's still allowed
+    fn is_current(&self, user: &BobUser) -> bool {
+        match (&user.cluster_config, self.runtime.cluster()) {
+            (Some(logged_into), Some(current)) => Arc::ptr_eq(logged_into, &current),
+            (None, None) => self.runtime.allow_hostname(),
+            _ => false,
+        }
+    }
+
+    /// Forgets the users whose sessions have expired, along with the sessions themselves
+    async fn forget_expired(&self) {
+        let now = Instant::now();
+        self.users
+            .write()
+            .await
+            .retain(|_, user| user.expires_at > now);
+        if let Err(err) = self.sessions.cleanup().await {
+            tracing::warn!("couldn't clean up the expired sessions: {err}");
+        }
+    }
+
+    /// Marks the session cookie `Secure`, which should be done when the server is served over TLS
+    #[must_use]
+    pub const fn with_secure_cookie(mut self, secure: bool) -> Self {
+        self.secure_cookie = secure;
+        self
+    }
+}
+
+/// Wraps `router` with session and authentication layers
+pub fn with_auth(router: Router<(), Body>, state: AuthState) -> Router<(), Body> {
+    let mut secret = [0; 64];
+    rand::thread_rng().fill(&mut secret[..]);
+
+    // Sessions are stored on login only, so that they expire with the users logged in by them
+    let session_layer = SessionLayer::new(state.sessions.clone(), &secret)
+        .with_secure(state.secure_cookie)
+        .with_session_ttl(Some(state.session_ttl))
+        .with_persistence_policy(PersistencePolicy::ChangedOnly);
+    let auth_layer = AuthLayer::new(MemoryStore::new(&state.users), &secret);
+
+    router
+        .layer(Extension(state))
+        .layer(auth_layer)
+        .layer(session_layer)
+}
+
+/// Provides the cluster client of the authenticated user to the handlers
+///
+/// Users logged into a cluster that has been reconfigured since are forgotten
+/// and responded with [`APIError::ClusterChanged`], so that they log in again
+pub async fn inject_cluster<B>(mut request: Request<B>, next: Next<B>) -> Response {
+    let extensions = request.extensions();
+    if let Some(user) = extensions.get::<BobUser>().cloned() {
+        if let Some(state) = extensions.get::<AuthState>().cloned() {
+            if !state.is_current(&user) {
+                state.users.write().await.remove(&user.id);
+                tracing::info!("user {} is logged out, the cluster has changed", user.login);
+                return APIError::ClusterChanged.into_response();
+            }
+        }
+        request.extensions_mut().insert(user.cluster);
+    }
+
+    next.run(request).await
+}
+
+/// Lets only the [admins](RuntimeConfig::is_admin) through, responding with [`APIError::Forbidden`]
+/// to the other users
+pub async fn require_admin<B>(request: Request<B>, next: Next<B>) -> Response {
+    let extensions = request.extensions();
+    match extensions
+        .get::<BobUser>()
+        .zip(extensions.get::<AuthState>())
+    {
+        Some((user, state)) if state.runtime.is_admin(&user.login) => next.run(request).await,
+        user => {
+            tracing::warn!(
+                "user {:?} isn't allowed to access {}",
+                user.map(|(user, _)| &user.login),
+                request.uri().path()
+            );
+            APIError::Forbidden.into_response()
+        }
+    }
+}
+
+/// Login to the Bob cluster
+///
+/// Credentials are checked against the basic-auth users of the cluster.
+/// The cluster is discovered from the configured bootstrap nodes or, if no cluster is configured
+/// and `auth.allow-hostname` is set, from the specified node
+///
+/// # Errors
+///
+/// This function will return an error if the node is specified but not allowed,
+/// or the cluster couldn't be reached or rejected the credentials
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        post,
+        context_path = "/api/v1",
+        path = "/login",
+        request_body = LoginRequest,
+        responses(
+            (status = 200, description = "Successful authorization"),
+            (status = 400, body = ProblemDetails, content_type = "application/problem+json", description = "Neither node nor cluster is specified, or the node isn't allowed"),
+            (status = 401, body = ProblemDetails, content_type = "application/problem+json", description = "Invalid credentials"),
+            (status = 500, body = ProblemDetails, content_type = "application/problem+json", description = "Session couldn't be started"),
+            (status = 503, body = ProblemDetails, content_type = "application/problem+json", description = "Cluster couldn't be reached"),
+            (status = 504, body = ProblemDetails, content_type = "application/problem+json", description = "Cluster didn't respond in time"),
+        )
+    ))]
+pub async fn login(
+    mut auth: BobAuthContext,
+    Extension(state): Extension<AuthState>,
+    Json(request): Json<LoginRequest>,
+) -> AxumResult<StatusCode> {
+    let credentials = Credentials::Basic {
+        login: request.login.clone(),
+        password: request.password,
+    };
+    let cluster_config = state.runtime.cluster();
+    let cluster = match (request.hostname, &cluster_config) {
+        // The configured cluster isn't bypassed, and arbitrary hosts aren't connected to by default
+        (Some(_), Some(_)) => return Err(APIError::HostnameNotAllowed.into()),
+        (Some(_), None) if !state.runtime.allow_hostname() => {
+            return Err(APIError::HostnameNotAllowed.into())
+        }
+        (Some(hostname), None) => {
+            async {
+                let bootstrap = BobClient::try_new(&hostname, state.runtime.request_timeout())?
+                    .with_runtime_timeout(&state.runtime)
+                    .with_credentials(credentials);
+                ClusterClient::discover(bootstrap).await
+            }
+            .await
+        }
+        (None, Some(cluster)) => {
+            ClusterClient::connect(&cluster.nodes, Some(credentials), &state.runtime).await
+        }
+        (None, None) => return Err(APIError::NoCluster.into()),
+    }
+    .map_err(|err| match err.current_context() {
+        ClientError::UnexpectedStatus(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+            tracing::warn!("{err:?}");
+            APIError::InvalidCredentials
+        }
+        _ => APIError::from(err),
+    })?;
+
+    let user = {
+        let mut rng = rand::thread_rng();
+        let mut secret = vec![0; 32];
+        rng.fill(&mut secret[..]);
+        BobUser {
+            id: rng.gen(),
+            login: request.login,
+            secret,
+            cluster,
+            cluster_config,
+            expires_at: Instant::now() + state.session_ttl,
+        }
+    };
+    // Every login adds a user, so the expired ones are swept here to keep their number bounded
+    state.forget_expired().await;
+    state.users.write().await.insert(user.id, user.clone());
+    auth.login(&user).await.map_err(|err| {
+        tracing::error!("couldn't start the session of {}: {err}", user.login);
+        APIError::Internal
+    })?;
+    tracing::info!("user {} logged in", user.login);
+
+    Ok(StatusCode::OK)
+}
+
+/// Logout from the Bob cluster
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        post,
+        context_path = "/api/v1",
+        path = "/logout",
+        responses(
+            (status = 200, description = "Logged out"),
+        )
+    ))]
+pub async fn logout(mut auth: BobAuthContext, Extension(state): Extension<AuthState>) -> Response {
+    if let Some(user) = auth.current_user.take() {
+        state.users.write().await.remove(&user.id);
+        tracing::info!("user {} logged out", user.login);
+    }
+    auth.logout().await;
+
+    StatusCode::OK.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::{
+        connector::mock::{spawn_node, MockNode},
+        services::api_router_v1,
+    };
+    use axum::{
+        body::BoxBody,
+        http::{
+            header::{AUTHORIZATION, CONTENT_TYPE, COOKIE, SET_COOKIE},
+            HeaderMap,
+        },
+        routing::get,
+    };
+    use cli::{AuthConfig, ClusterConfig, Config};
+    use hyper::Method;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    /// Mock node that accepts `admin:password` only
+    fn spawn_secured_node() -> String {
+        let router = MockNode::new("node1", &[0])
+            .router()
+            .layer(axum::middleware::from_fn(
+                |request: Request<Body>, next: Next<Body>| async move {
+                    if request
+                        .headers()
+                        .get(AUTHORIZATION)
+                        .map(|auth| auth.as_bytes())
+                        == Some(b"Basic YWRtaW46cGFzc3dvcmQ=")
+                    {
+                        next.run(request).await
+                    } else {
+                        StatusCode::UNAUTHORIZED.into_response()
+                    }
+                },
+            ));
+        spawn_node(router).to_string()
+    }
+
+    fn app() -> Router {
+        app_with_cluster(None)
+    }
+
+    fn app_with_cluster(cluster: Option<ClusterConfig>) -> Router {
+        with_auth(
+            api_router_v1().unwrap(),
+            AuthState::new(RuntimeConfig::new(&Config {
+                auth: AuthConfig {
+                    allow_hostname: true,
+                    admins: vec![],
+                },
+                cluster,
+                request_timeout: Duration::from_secs(1),
+                ..Config::default()
+            })),
+        )
+    }
+
+    async fn send(
+        app: &Router,
+        method: Method,
+        uri: &str,
+        cookie: Option<&str>,
+        body: Option<String>,
+    ) -> Response<BoxBody> {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(cookie) = cookie {
+            request = request.header(COOKIE, cookie);
+        }
+        if body.is_some() {
+            request = request.header(CONTENT_TYPE, "application/json");
+        }
+        app.clone()
+            .oneshot(
+                request
+                    .body(body.map_or_else(Body::empty, Body::from))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    fn login_body(hostname: &str, password: &str) -> Option<String> {
+        Some(
+            serde_json::json!({
+                "hostname": hostname,
+                "login": "admin",
+                "password": password
+            })
+            .to_string(),
+        )
+    }
+
+    fn session_cookie(headers: &HeaderMap) -> String {
+        headers
+            .get(SET_COOKIE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn unauthenticated_request() {
+        let response = send(&app(), Method::GET, "/cluster", None, None).await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[CONTENT_TYPE], crate::error::PROBLEM_JSON);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let problem: crate::error::ProblemDetails = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem.code, "unauthorized");
+    }
+
+    #[tokio::test]
+    async fn invalid_credentials() {
+        let node = spawn_secured_node();
+        let response = send(
+            &app(),
+            Method::POST,
+            "/login",
+            None,
+            login_body(&node, "wrong"),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn login_and_logout() {
+        let app = app();
+        let node = spawn_secured_node();
+
+        let response = send(
+            &app,
+            Method::POST,
+            "/login",
+            None,
+            login_body(&node, "password"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let cookie = session_cookie(response.headers());
+
+        let response = send(&app, Method::GET, "/cluster", Some(&cookie), None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(&app, Method::POST, "/logout", Some(&cookie), None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(&app, Method::GET, "/cluster", Some(&cookie), None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn expired_sessions() {
+        let node = spawn_secured_node();
+        let state = AuthState::new(RuntimeConfig::new(&Config {
+            auth: AuthConfig {
+                allow_hostname: true,
+                admins: vec![],
+            },
+            ..Config::default()
+        }))
+        .with_session_ttl(Duration::from_millis(300));
+        let app = with_auth(api_router_v1().unwrap(), state.clone());
+        let login = || {
+            send(
+                &app,
+                Method::POST,
+                "/login",
+                None,
+                login_body(&node, "password"),
+            )
+        };
+
+        let response = login().await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let cookie = session_cookie(response.headers());
+        let expired_id = *state.users.read().await.keys().next().unwrap();
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        let response = send(&app, Method::GET, "/cluster", Some(&cookie), None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        assert_eq!(login().await.status(), StatusCode::OK);
+        let users = state.users.read().await;
+        assert_eq!(users.len(), 1);
+        assert!(!users.contains_key(&expired_id));
+    }
+
+    #[tokio::test]
+    async fn secure_cookie() {
+        let node = spawn_secured_node();
+        for secure in [false, true] {
+            let app = with_auth(
+                api_router_v1().unwrap(),
+                AuthState::new(RuntimeConfig::new(&Config {
+                    auth: AuthConfig {
+                        allow_hostname: true,
+                        admins: vec![],
+                    },
+                    ..Config::default()
+                }))
+                .with_secure_cookie(secure),
+            );
+            let response = send(
+                &app,
+                Method::POST,
+                "/login",
+                None,
+                login_body(&node, "password"),
+            )
+            .await;
+
+            let cookie = response.headers()[SET_COOKIE].to_str().unwrap();
+            assert_eq!(cookie.contains("; Secure"), secure, "{cookie}");
+        }
+    }
+
+    #[tokio::test]
+    async fn inject_cluster_without_user() {
+        let response = Router::new()
+            .route(
+                "/",
+                get(|cluster: Option<Extension<ClusterClient>>| async move {
+                    cluster.map_or(StatusCode::NO_CONTENT, |_| StatusCode::OK)
+                }),
+            )
+            .layer(axum::middleware::from_fn(inject_cluster))
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn login_into_configured_cluster() {
+        let app = app_with_cluster(Some(ClusterConfig {
+            nodes: vec![cli::BootstrapNode {
+                address: spawn_secured_node(),
+                grpc_port: None,
+            }],
+            credentials: None,
+            token: None,
+        }));
+        let body = Some(r#"{"login": "admin", "password": "password"}"#.to_string());
+
+        let response = send(&app, Method::POST, "/login", None, body).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn cluster_change_ends_sessions() {
+        let config = |address: String| Config {
+            cluster: Some(ClusterConfig {
+                nodes: vec![cli::BootstrapNode {
+                    address,
+                    grpc_port: None,
+                }],
+                credentials: None,
+                token: None,
+            }),
+            ..Config::default()
+        };
+        let node = spawn_secured_node();
+        let runtime = RuntimeConfig::new(&config(node.clone()));
+        let state = AuthState::new(runtime.clone());
+        let app = with_auth(api_router_v1().unwrap(), state.clone());
+        let body = Some(r#"{"login": "admin", "password": "password"}"#.to_string());
+
+        let response = send(&app, Method::POST, "/login", None, body).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let cookie = session_cookie(response.headers());
+
+        // Reloading the same cluster keeps the session
+        runtime.update(&config(node));
+        let response = send(&app, Method::GET, "/cluster", Some(&cookie), None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        runtime.update(&config(spawn_secured_node()));
+        let response = send(&app, Method::GET, "/cluster", Some(&cookie), None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let problem: crate::error::ProblemDetails = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem.code, "cluster_changed");
+        assert!(state.users.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn hostname_not_allowed() {
+        let node = spawn_secured_node();
+        let configured = app_with_cluster(Some(ClusterConfig {
+            nodes: vec![cli::BootstrapNode {
+                address: node.clone(),
+                grpc_port: None,
+            }],
+            credentials: None,
+            token: None,
+        }));
+        let disallowed = with_auth(
+            api_router_v1().unwrap(),
+            AuthState::new(RuntimeConfig::new(&Config::default())),
+        );
+
+        for app in [configured, disallowed] {
+            let response = send(
+                &app,
+                Method::POST,
+                "/login",
+                None,
+                login_body(&node, "password"),
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            let problem: crate::error::ProblemDetails = serde_json::from_slice(&body).unwrap();
+            assert_eq!(problem.code, "hostname_not_allowed");
+        }
+    }
+
+    #[tokio::test]
+    async fn admin_endpoints() {
+        let node = spawn_secured_node();
+        for (admins, status) in [
+            (vec![], StatusCode::FORBIDDEN),
+            (vec!["root".to_string()], StatusCode::FORBIDDEN),
+            (vec!["admin".to_string()], StatusCode::OK),
+        ] {
+            let app = with_auth(
+                api_router_v1()
+                    .unwrap()
+                    .layer(Extension(crate::services::logging::LogFilters::default())),
+                AuthState::new(RuntimeConfig::new(&Config {
+                    auth: AuthConfig {
+                        allow_hostname: true,
+                        admins: admins.clone(),
+                    },
+                    ..Config::default()
+                })),
+            );
+            let response = send(
+                &app,
+                Method::POST,
+                "/login",
+                None,
+                login_body(&node, "password"),
+            )
+            .await;
+            let cookie = session_cookie(response.headers());
+
+            let response = send(&app, Method::GET, "/admin/log-filters", Some(&cookie), None).await;
+            assert_eq!(response.status(), status, "{admins:?}");
+            let response = send(&app, Method::GET, "/admin/log-filters", None, None).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{admins:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn login_without_cluster() {
+        let body = Some(r#"{"login": "admin", "password": "password"}"#.to_string());
+        let response = send(&app(), Method::POST, "/login", None, body).await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,153 @@
+use super::APIError;
+use crate::{connector::ClusterClient, prelude::*};
+use axum::{extract::Path, Extension};
+use hyper::StatusCode;
+
+/// Stops the node's disk for maintenance
+///
+/// # Errors
+///
+/// This function will return an error if the node or the disk doesn't exist
+/// or the node rejected the request
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        post,
+        context_path = "/api/v1",
+        path = "/nodes/{node_name}/disks/{disk_name}/stop",
+        params(
+            ("node_name" = String, Path, description = "Name of the node"),
+            ("disk_name" = String, Path, description = "Name of the node's disk")
+        ),
+        responses(
+            (status = 200, description = "Disk stopped"),
+            (status = 403, body = ProblemDetails, content_type = "application/problem+json", description = "Not enough permissions"),
+            (status = 404, body = ProblemDetails, content_type = "application/problem+json", description = "Node or disk not found"),
+            (status = 502, body = ProblemDetails, content_type = "application/problem+json", description = "Node failed to stop the disk"),
+            (status = 503, body = ProblemDetails, content_type = "application/problem+json", description = "Node couldn't be reached"),
+            (status = 504, body = ProblemDetails, content_type = "application/problem+json", description = "Node didn't respond in time")
+        )
+    ))]
+pub async fn stop_disk(
+    Extension(cluster): Extension<ClusterClient>,
+    Path((node_name, disk_name)): Path<(String, String)>,
+) -> AxumResult<StatusCode> {
+    let client = cluster.node(&node_name).ok_or(APIError::NotFound)?;
+    client
+        .stop_disk(&disk_name)
+        .await
+        .map_err(|err| APIError::node_problem(&node_name, err))?;
+    tracing::info!("disk {disk_name} stopped on {node_name}");
+
+    Ok(StatusCode::OK)
+}
+
+/// Starts previously stopped disk of the node
+///
+/// # Errors
+///
+/// This function will return an error if the node or the disk doesn't exist
+/// or the node rejected the request
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        post,
+        context_path = "/api/v1",
+        path = "/nodes/{node_name}/disks/{disk_name}/start",
+        params(
+            ("node_name" = String, Path, description = "Name of the node"),
+            ("disk_name" = String, Path, description = "Name of the node's disk")
+        ),
+        responses(
+            (status = 200, description = "Disk started"),
+            (status = 403, body = ProblemDetails, content_type = "application/problem+json", description = "Not enough permissions"),
+            (status = 404, body = ProblemDetails, content_type = "application/problem+json", description = "Node or disk not found"),
+            (status = 502, body = ProblemDetails, content_type = "application/problem+json", description = "Node failed to start the disk"),
+            (status = 503, body = ProblemDetails, content_type = "application/problem+json", description = "Node couldn't be reached"),
+            (status = 504, body = ProblemDetails, content_type = "application/problem+json", description = "Node didn't respond in time")
+        )
+    ))]
+pub async fn start_disk(
+    Extension(cluster): Extension<ClusterClient>,
+    Path((node_name, disk_name)): Path<(String, String)>,
+) -> AxumResult<StatusCode> {
+    let client = cluster.node(&node_name).ok_or(APIError::NotFound)?;
+    client
+        .start_disk(&disk_name)
+        .await
+        .map_err(|err| APIError::node_problem(&node_name, err))?;
+    tracing::info!("disk {disk_name} started on {node_name}");
+
+    Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::connector::{mock::spawn_node, BobClient};
+    use axum::{routing::post, Router};
+    use hyper::{Body, Request};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    /// Router with a single node, which has `disk1` only and doesn't allow to stop it
+    fn app() -> Router {
+        let node = spawn_node(
+            Router::new()
+                .route(
+                    "/disks/:disk_name/stop",
+                    post(|Path(disk): Path<String>| async move {
+                        if disk == "disk1" {
+                            StatusCode::FORBIDDEN
+                        } else {
+                            StatusCode::NOT_FOUND
+                        }
+                    }),
+                )
+                .route(
+                    "/disks/:disk_name/start",
+                    post(|Path(disk): Path<String>| async move {
+                        if disk == "disk1" {
+                            StatusCode::OK
+                        } else {
+                            StatusCode::NOT_FOUND
+                        }
+                    }),
+                ),
+        );
+        let client = BobClient::try_new(&node.to_string(), Duration::from_secs(1)).unwrap();
+
+        Router::new()
+            .route("/nodes/:node_name/disks/:disk_name/stop", post(stop_disk))
+            .route("/nodes/:node_name/disks/:disk_name/start", post(start_disk))
+            .layer(Extension(ClusterClient::new(
+                client.clone(),
+                [("node1".to_string(), client)],
+            )))
+    }
+
+    async fn status(uri: &str) -> StatusCode {
+        app()
+            .oneshot(Request::post(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn disk_operations() {
+        assert_eq!(
+            status("/nodes/node1/disks/disk1/start").await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status("/nodes/node1/disks/disk1/stop").await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status("/nodes/node1/disks/disk2/start").await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status("/nodes/node2/disks/disk1/start").await,
+            StatusCode::NOT_FOUND
+        );
+    }
+}
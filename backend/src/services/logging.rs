@@ -0,0 +1,305 @@
+use super::APIError;
+use crate::{
+    config::env_filter,
+    models::api::{LogFilter, LogFilterUpdate},
+    prelude::*,
+};
+use axum::{Extension, Json};
+use std::{
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::task::JoinHandle;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// Name of the rolling file output
+pub const FILE_OUTPUT: &str = "file";
+
+/// Name of the stdout output
+pub const STDOUT_OUTPUT: &str = "stdout";
+
+/// Longest delay of the configured filter's restoration
+pub const MAX_REVERT_AFTER: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+type FilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Filters of the log outputs, which can be changed at runtime
+#[derive(Clone, Debug, Default)]
+pub struct LogFilters {
+    outputs: Arc<Mutex<Vec<Output>>>,
+}
+
+#[derive(Debug)]
+struct Output {
+    name: &'static str,
+    handle: FilterHandle,
+    directives: String,
+    configured: String,
+    /// Unix timestamp in milliseconds and the task that restores the configured directives
+    revert: Option<(u64, JoinHandle<()>)>,
+}
+
+impl LogFilters {
+    /// Registers the output's filter, installed with the `directives`
+    pub fn add(&self, name: &'static str, directives: String, handle: FilterHandle) {
+        self.lock().push(Output {
+            name,
+            handle,
+            configured: directives.clone(),
+            directives,
+            revert: None,
+        });
+    }
+
+    /// Active filters of every output
+    #[must_use]
+    pub fn get(&self) -> Vec<LogFilter> {
+        self.lock().iter().map(Output::filter).collect()
+    }
+
+    /// Replaces the filter of the `output`, or of every output if it's not specified.
+    /// The configured filter is restored after `revert_after`, if specified
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the directives are invalid, `revert_after` exceeds
+    /// [`MAX_REVERT_AFTER`] or the output doesn't exist
+    pub fn set(
+        &self,
+        output: Option<&str>,
+        directives: &str,
+        revert_after: Option<Duration>,
+    ) -> std::result::Result<Vec<LogFilter>, APIError> {
+        env_filter(directives).map_err(|err| {
+            tracing::warn!("{err:?}");
+            APIError::InvalidLogFilter
+        })?;
+        let revert_at = match revert_after {
+            Some(after) if after > MAX_REVERT_AFTER => return Err(APIError::InvalidLogFilter),
+            Some(after) => Some(
+                SystemTime::now()
+                    .checked_add(after)
+                    .ok_or(APIError::InvalidLogFilter)?,
+            ),
+            None => None,
+        };
+
+        let mut outputs = self.lock();
+        if output.is_some_and(|output| !outputs.iter().any(|o| o.name == output)) {
+            return Err(APIError::NotFound);
+        }
+        for target in outputs
+            .iter_mut()
+            .filter(|o| output.is_none() || output == Some(o.name))
+        {
+            target.reload(directives.to_string());
+            if let Some((_, task)) = target.revert.take() {
+                task.abort();
+            }
+            if let Some((revert_after, revert_at)) = revert_after.zip(revert_at) {
+                target.revert = Some((
+                    timestamp(revert_at),
+                    self.spawn_revert(target.name, revert_after),
+                ));
+            }
+            tracing::info!(
+                "{} log filter changed to `{directives}`, reverting after {revert_after:?}",
+                target.name
+            );
+        }
+
+        Ok(outputs.iter().map(Output::filter).collect())
+    }
+
+    /// Replaces the configured filter of the output, which is installed
+    /// unless the filter was changed at runtime and is yet to be reverted
+    pub fn configure(&self, name: &str, directives: String) {
+        let mut outputs = self.lock();
+        let Some(output) = outputs.iter_mut().find(|o| o.name == name) else {
+            return;
+        };
+        if output.configured == directives {
+            return;
+        }
+        if output.revert.is_none() && output.directives == output.configured {
+            output.reload(directives.clone());
+        }
+        tracing::info!("{name} log filter configured to `{directives}`");
+        output.configured = directives;
+    }
+
+    fn spawn_revert(&self, name: &'static str, after: Duration) -> JoinHandle<()> {
+        let filters = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(after).await;
+            if let Some(output) = filters.lock().iter_mut().find(|o| o.name == name) {
+                output.reload(output.configured.clone());
+                output.revert = None;
+                tracing::info!("{name} log filter reverted to `{}`", output.configured);
+            }
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Output>> {
+        self.outputs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Output {
+    /// Installs the already validated `directives`
+    fn reload(&mut self, directives: String) {
+        match EnvFilter::try_new(&directives) {
+            Ok(filter) => match self.handle.reload(filter) {
+                Ok(()) => self.directives = directives,
+                Err(err) => tracing::error!("couldn't reload {} log filter: {err}", self.name),
+            },
+            Err(err) => tracing::error!("invalid {} log filter: {err}", self.name),
+        }
+    }
+
+    fn filter(&self) -> LogFilter {
+        LogFilter {
+            output: self.name.to_string(),
+            directives: self.directives.clone(),
+            configured: self.configured.clone(),
+            revert_at: self.revert.as_ref().map(|(at, _)| *at),
+        }
+    }
+}
+
+fn timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis().try_into().unwrap_or(u64::MAX))
+}
+
+/// Returns the active filter directives of the log outputs
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        get,
+        context_path = "/api/v1",
+        path = "/admin/log-filters",
+        responses(
+            (status = 200, body = [LogFilter], content_type = "application/json", description = "Log outputs' filters"),
+            (status = 403, body = ProblemDetails, content_type = "application/problem+json", description = "The user isn't an admin"),
+        )
+    ))]
+pub async fn get_log_filters(Extension(filters): Extension<LogFilters>) -> Json<Vec<LogFilter>> {
+    Json(filters.get())
+}
+
+/// Changes the filter directives of the log outputs, optionally restoring the configured ones later
+///
+/// # Errors
+///
+/// This function will return an error if the directives or the revert delay are invalid,
+/// or the output doesn't exist
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        put,
+        context_path = "/api/v1",
+        path = "/admin/log-filters",
+        request_body = LogFilterUpdate,
+        responses(
+            (status = 200, body = [LogFilter], content_type = "application/json", description = "Log outputs' filters"),
+            (status = 400, body = ProblemDetails, content_type = "application/problem+json", description = "Invalid filter directives or revert delay"),
+            (status = 403, body = ProblemDetails, content_type = "application/problem+json", description = "The user isn't an admin"),
+            (status = 404, body = ProblemDetails, content_type = "application/problem+json", description = "Output not found")
+        )
+    ))]
+pub async fn set_log_filters(
+    Extension(filters): Extension<LogFilters>,
+    Json(update): Json<LogFilterUpdate>,
+) -> AxumResult<Json<Vec<LogFilter>>> {
+    Ok(Json(filters.set(
+        update.output.as_deref(),
+        &update.directives,
+        update.revert_after.map(Duration::from_secs),
+    )?))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use axum::{routing::get, Router};
+    use hyper::{body::to_bytes, header::CONTENT_TYPE, Body, Method, Request, StatusCode};
+    use tower::ServiceExt;
+    use tracing_subscriber::{fmt, prelude::*};
+
+    async fn send(router: &Router, method: Method, body: &str) -> (StatusCode, Vec<u8>) {
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri("/admin/log-filters")
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        (
+            response.status(),
+            to_bytes(response.into_body()).await.unwrap().to_vec(),
+        )
+    }
+
+    #[tokio::test]
+    async fn change_and_revert() {
+        let filters = LogFilters::default();
+        let (file_filter, handle) = reload::Layer::new(EnvFilter::new("info"));
+        filters.add(FILE_OUTPUT, "info".to_string(), handle);
+        let (stdout_filter, handle) = reload::Layer::new(EnvFilter::new("warn"));
+        filters.add(STDOUT_OUTPUT, "warn".to_string(), handle);
+        let _subscriber = tracing_subscriber::registry().with(vec![
+            fmt::layer().with_filter(file_filter).boxed(),
+            fmt::layer().with_filter(stdout_filter).boxed(),
+        ]);
+        let router = Router::new()
+            .route(
+                "/admin/log-filters",
+                get(get_log_filters).put(set_log_filters),
+            )
+            .layer(Extension(filters.clone()));
+
+        let (status, body) = send(
+            &router,
+            Method::PUT,
+            r#"{"output": "file", "directives": "debug", "revert_after": 1}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let outputs: Vec<LogFilter> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(outputs[0].directives, "debug");
+        assert!(outputs[0].revert_at.is_some());
+        assert_eq!(outputs[1].directives, "warn");
+
+        let (status, _) = send(&router, Method::PUT, r#"{"directives": "bob=loud"}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        for revert_after in [MAX_REVERT_AFTER.as_secs() + 1, u64::MAX] {
+            let (status, _) = send(
+                &router,
+                Method::PUT,
+                &format!(r#"{{"directives": "trace", "revert_after": {revert_after}}}"#),
+            )
+            .await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{revert_after}");
+        }
+        let (_, body) = send(&router, Method::GET, "").await;
+        let outputs: Vec<LogFilter> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(outputs[1].directives, "warn");
+        let (status, _) = send(
+            &router,
+            Method::PUT,
+            r#"{"output": "syslog", "directives": "debug"}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let (_, body) = send(&router, Method::GET, "").await;
+        let outputs: Vec<LogFilter> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(outputs[0].directives, "info");
+        assert_eq!(outputs[0].revert_at, None);
+    }
+}
@@ -0,0 +1,356 @@
+use super::APIError;
+use crate::{
+    connector::{dto, ClusterClient, Credentials},
+    models::api::{ClusterMetrics, MetricsSample, NodeMetrics},
+    prelude::*,
+    reload::RuntimeConfig,
+};
+use axum::{extract::Path, Extension, Json};
+use cli::MetricsConfig;
+use futures::future::join_all;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    sync::RwLock,
+    task::JoinHandle,
+    time::{interval, MissedTickBehavior},
+};
+
+/// Latest metrics samples of the cluster's nodes, filled by the background scraper
+#[derive(Clone, Debug)]
+pub struct MetricsStore {
+    nodes: Arc<RwLock<BTreeMap<String, VecDeque<MetricsSample>>>>,
+    capacity: usize,
+    stale_after: Option<Duration>,
+}
+
+impl MetricsStore {
+    /// Keeps up to `capacity` latest samples of every node
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            nodes: Arc::default(),
+            capacity,
+            stale_after: None,
+        }
+    }
+
+    /// Leaves the nodes whose latest sample is older than `stale_after` out of the cluster's metrics
+    #[must_use]
+    pub const fn with_stale_after(mut self, stale_after: Duration) -> Self {
+        self.stale_after = Some(stale_after);
+        self
+    }
+
+    /// Scrapes the currently configured cluster in the background until the task is aborted
+    ///
+    /// Connection to the cluster is retried on every tick until it succeeds,
+    /// and made anew once the cluster changes
+    #[must_use]
+    pub fn spawn_scraper(self, runtime: RuntimeConfig, config: &MetricsConfig) -> JoinHandle<()> {
+        let mut ticker = interval(config.scrape_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        tokio::spawn(async move {
+            let mut client = None;
+            let mut connected = None;
+            loop {
+                ticker.tick().await;
+                let current = runtime.cluster();
+                if connected.as_ref() != Some(&current) {
+                    client = None;
+                }
+                let Some(cluster) = &current else {
+                    continue;
+                };
+                if client.is_none() {
+                    client = ClusterClient::connect(
+                        &cluster.nodes,
+                        Credentials::from_config(cluster),
+                        &runtime,
+                    )
+                    .await
+                    .map_err(|err| tracing::warn!("metrics scraper: {err:?}"))
+                    .ok();
+                    connected = Some(current.clone());
+                }
+                if let Some(client) = &client {
+                    self.scrape(client).await;
+                }
+            }
+        })
+    }
+
+    /// Requests metrics from every node of the cluster concurrently.
+    /// Nodes that failed to respond are skipped until the next scrape,
+    /// samples of the nodes that aren't in the cluster anymore are dropped
+    pub async fn scrape(&self, cluster: &ClusterClient) {
+        let results = join_all(
+            cluster
+                .nodes()
+                .map(|(name, client)| async move { (name, client.get_metrics().await) }),
+        )
+        .await;
+        let timestamp = now();
+
+        self.nodes
+            .write()
+            .await
+            .retain(|node, _| results.iter().any(|(name, _)| *name == node));
+        for (name, result) in results {
+            match result {
+                Ok(metrics) => self.push(name, timestamp, metrics).await,
+                Err(err) => tracing::warn!("node {name}: {err:?}"),
+            }
+        }
+    }
+
+    /// Stores the node's metrics scraped at `timestamp`, in milliseconds,
+    /// computing rates against the previous sample
+    pub async fn push(&self, node: &str, timestamp: u64, metrics: dto::MetricsSnapshot) {
+        let mut nodes = self.nodes.write().await;
+        let samples = nodes.entry(node.to_string()).or_default();
+        let rates = samples
+            .back()
+            .map(|previous| rates(previous, timestamp, &metrics.counters))
+            .unwrap_or_default();
+        if samples.len() == self.capacity {
+            samples.pop_front();
+        }
+        samples.push_back(MetricsSample {
+            timestamp,
+            counters: metrics.counters,
+            gauges: metrics.gauges,
+            rates,
+        });
+    }
+
+    /// Latest samples of the node, `None` if the node has never been scraped
+    pub async fn node(&self, node: &str) -> Option<NodeMetrics> {
+        self.nodes
+            .read()
+            .await
+            .get(node)
+            .map(|samples| NodeMetrics {
+                node: node.to_string(),
+                samples: samples.iter().cloned().collect(),
+            })
+    }
+
+    /// Latest sample of every node, summed. Stale samples are skipped, see [`Self::with_stale_after`]
+    pub async fn cluster(&self) -> ClusterMetrics {
+        let oldest = self.stale_after.map_or(0, |stale_after| {
+            now().saturating_sub(stale_after.as_millis().try_into().unwrap_or(u64::MAX))
+        });
+        let mut metrics = ClusterMetrics::default();
+        for sample in self
+            .nodes
+            .read()
+            .await
+            .values()
+            .filter_map(VecDeque::back)
+            .filter(|sample| sample.timestamp >= oldest)
+        {
+            metrics.nodes_count += 1;
+            for (name, value) in &sample.counters {
+                *metrics.counters.entry(name.clone()).or_default() += value;
+            }
+            for (name, value) in &sample.gauges {
+                *metrics.gauges.entry(name.clone()).or_default() += value;
+            }
+            for (name, value) in &sample.rates {
+                *metrics.rates.entry(name.clone()).or_default() += value;
+            }
+        }
+
+        metrics
+    }
+}
+
+/// Current time in milliseconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis().try_into().unwrap_or(u64::MAX))
+}
+
+/// Per-second increase of the `counters` since the `previous` sample.
+/// Counters that decreased were reset by the node's restart and are skipped
+#[allow(clippy::cast_precision_loss)]
+fn rates(
+    previous: &MetricsSample,
+    timestamp: u64,
+    counters: &HashMap<String, u64>,
+) -> HashMap<String, f64> {
+    let elapsed = timestamp.saturating_sub(previous.timestamp) as f64 / 1000.0;
+    if elapsed == 0.0 {
+        return HashMap::new();
+    }
+
+    counters
+        .iter()
+        .filter_map(|(name, value)| {
+            let increase = value.checked_sub(*previous.counters.get(name)?)?;
+            Some((name.clone(), increase as f64 / elapsed))
+        })
+        .collect()
+}
+
+/// Returns the latest metrics samples of the node
+///
+/// # Errors
+///
+/// This function will return an error if the node has never been scraped
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        get,
+        context_path = "/api/v1",
+        path = "/metrics/{node_name}",
+        params(
+            ("node_name" = String, Path, description = "Name of the node")
+        ),
+        responses(
+            (status = 200, body = NodeMetrics, content_type = "application/json", description = "Node's metrics"),
+            (status = 404, body = ProblemDetails, content_type = "application/problem+json", description = "Node not found")
+        )
+    ))]
+pub async fn get_node_metrics(
+    Extension(store): Extension<MetricsStore>,
+    Path(node_name): Path<String>,
+) -> AxumResult<Json<NodeMetrics>> {
+    Ok(Json(
+        store.node(&node_name).await.ok_or(APIError::NotFound)?,
+    ))
+}
+
+/// Returns the latest metrics of the cluster's nodes, summed
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        get,
+        context_path = "/api/v1",
+        path = "/metrics",
+        responses(
+            (status = 200, body = ClusterMetrics, content_type = "application/json", description = "Cluster's metrics"),
+        )
+    ))]
+pub async fn get_cluster_metrics(
+    Extension(store): Extension<MetricsStore>,
+) -> Json<ClusterMetrics> {
+    Json(store.cluster().await)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::float_cmp)]
+    use super::*;
+    use crate::connector::{
+        mock::{spawn_node, MockNode},
+        BobClient,
+    };
+    use axum::{routing::get, Router};
+    use hyper::{body::to_bytes, Body, Request, StatusCode};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    fn snapshot(counters: &[(&str, u64)]) -> dto::MetricsSnapshot {
+        dto::MetricsSnapshot {
+            counters: counters
+                .iter()
+                .map(|(name, value)| ((*name).to_string(), *value))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn rates_and_capacity() {
+        let store = MetricsStore::new(2);
+        store.push("node1", 1000, snapshot(&[("put", 10)])).await;
+        store
+            .push("node1", 3000, snapshot(&[("put", 30), ("get", 5)]))
+            .await;
+        store.push("node1", 4000, snapshot(&[("put", 2)])).await;
+
+        let samples = store.node("node1").await.unwrap().samples;
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].rates, HashMap::from([("put".to_string(), 10.0)]));
+        assert!(samples[1].rates.is_empty());
+        assert!(store.node("node2").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn cluster_sum() {
+        let store = MetricsStore::new(10);
+        for (node, put) in [("node1", 10), ("node2", 20)] {
+            store.push(node, 0, snapshot(&[("put", 0)])).await;
+            store.push(node, 1000, snapshot(&[("put", put)])).await;
+        }
+
+        let metrics = store.cluster().await;
+        assert_eq!(metrics.nodes_count, 2);
+        assert_eq!(metrics.counters["put"], 30);
+        assert_eq!(metrics.rates["put"], 30.0);
+    }
+
+    #[tokio::test]
+    async fn stale_and_removed_nodes() {
+        let store = MetricsStore::new(10).with_stale_after(Duration::from_secs(10));
+        store.push("node1", now(), snapshot(&[("put", 1)])).await;
+        store
+            .push("node2", now() - 60_000, snapshot(&[("put", 2)]))
+            .await;
+
+        let metrics = store.cluster().await;
+        assert_eq!(metrics.nodes_count, 1);
+        assert_eq!(metrics.counters["put"], 1);
+
+        let node = spawn_node(MockNode::new("node1", &[0]).router().route(
+            "/metrics",
+            get(|| async { axum::Json(snapshot(&[("put", 3)])) }),
+        ));
+        let client = BobClient::try_new(&node.to_string(), Duration::from_secs(1)).unwrap();
+        store
+            .scrape(&ClusterClient::new(
+                client.clone(),
+                [("node1".to_string(), client)],
+            ))
+            .await;
+        assert_eq!(store.node("node1").await.unwrap().samples.len(), 2);
+        assert!(store.node("node2").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn scrape_nodes() {
+        let metrics = snapshot(&[("put", 1)]);
+        let node = spawn_node(
+            MockNode::new("node1", &[0])
+                .router()
+                .route("/metrics", get(move || async move { axum::Json(metrics) })),
+        );
+        let client = BobClient::try_new(&node.to_string(), Duration::from_secs(1)).unwrap();
+        let cluster = ClusterClient::new(client.clone(), [("node1".to_string(), client)]);
+        let store = MetricsStore::new(10);
+        store.scrape(&cluster).await;
+
+        let router = Router::new()
+            .route("/metrics", get(get_cluster_metrics))
+            .route("/metrics/:node_name", get(get_node_metrics))
+            .layer(Extension(store));
+        let response = router
+            .clone()
+            .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let metrics: ClusterMetrics =
+            serde_json::from_slice(&to_bytes(response.into_body()).await.unwrap()).unwrap();
+        assert_eq!(metrics.counters["put"], 1);
+
+        let response = router
+            .oneshot(Request::get("/metrics/node2").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -1,31 +1,417 @@
-use crate::prelude::*;
+use crate::{
+    connector::ClientError,
+    deadline,
+    error::{problem_responses, ProblemDetails},
+    prelude::*,
+    reload::RuntimeConfig,
+    router::{ApiV1, RouterApiExt},
+    ApiDoc,
+};
 use axum::{
+    extract::{OriginalUri, State},
+    middleware::{from_fn, from_fn_with_state, Next},
     response::{IntoResponse, Response},
     Router,
 };
-use hyper::{Body, StatusCode};
+use hyper::{Body, Method, Request, StatusCode};
+use std::convert::Infallible;
 use thiserror::Error;
+use tokio::time::{Duration, Instant};
+use tower::{timeout::TimeoutLayer, Layer, ServiceExt};
+
+pub mod aliens;
+pub mod api;
+pub mod auth;
+pub mod disks;
+pub mod logging;
+pub mod metrics;
+pub mod partitions;
+
+use aliens::{detach_aliens, get_aliens_summary, get_node_aliens, sync_aliens};
+use api::{get_cluster, get_node};
+use auth::{inject_cluster, login, logout, require_admin, BobUser};
+use axum_login::RequireAuthorizationLayer;
+use disks::{start_disk, stop_disk};
+use logging::{get_log_filters, set_log_filters};
+use metrics::{get_cluster_metrics, get_node_metrics};
+use partitions::{delete_old_partitions, delete_partition, get_partitions};
 
 /// Export all secured routes
+///
+/// # Errors
+///
+/// This function will return an error if one of the routes doesn't match its `OpenAPI` declaration
 #[allow(dead_code)]
 pub fn api_router_v1() -> Result<Router<(), Body>, RouteError> {
-    Ok(Router::new())
+    Router::new()
+        .with_context::<ApiV1, ApiDoc>()
+        .api_route("/admin/log-filters", &Method::GET, get_log_filters)
+        .api_route("/admin/log-filters", &Method::PUT, set_log_filters)
+        .unwrap()?
+        // Layers wrap the routes registered before them
+        .route_layer(from_fn(require_admin))
+        .with_context::<ApiV1, ApiDoc>()
+        .api_route("/cluster", &Method::GET, get_cluster)
+        .api_route("/nodes/:node_name", &Method::GET, get_node)
+        .api_route(
+            "/nodes/:node_name/disks/:disk_name/stop",
+            &Method::POST,
+            stop_disk,
+        )
+        .api_route(
+            "/nodes/:node_name/disks/:disk_name/start",
+            &Method::POST,
+            start_disk,
+        )
+        .api_route("/vdisks/:vdisk_id/partitions", &Method::GET, get_partitions)
+        .api_route(
+            "/vdisks/:vdisk_id/partitions",
+            &Method::DELETE,
+            delete_old_partitions,
+        )
+        .api_route(
+            "/nodes/:node_name/vdisks/:vdisk_id/partitions/:timestamp",
+            &Method::DELETE,
+            delete_partition,
+        )
+        .api_route("/aliens", &Method::GET, get_aliens_summary)
+        .api_route("/nodes/:node_name/aliens", &Method::GET, get_node_aliens)
+        .api_route("/nodes/:node_name/aliens/sync", &Method::POST, sync_aliens)
+        .api_route(
+            "/nodes/:node_name/aliens/detach",
+            &Method::POST,
+            detach_aliens,
+        )
+        .api_route("/metrics", &Method::GET, get_cluster_metrics)
+        .api_route("/metrics/:node_name", &Method::GET, get_node_metrics)
+        .unwrap()?
+        .route_layer(from_fn(inject_cluster))
+        .route_layer(RequireAuthorizationLayer::<u64, BobUser>::login())
+        .with_context::<ApiV1, ApiDoc>()
+        .api_route("/login", &Method::POST, login)
+        .api_route("/logout", &Method::POST, logout)
+        .unwrap()
+        .map(|router| {
+            router
+                .fallback(api_not_found)
+                .layer(from_fn(problem_responses))
+        })
+}
+
+/// Time the handlers are given past the deadline of a request
+/// to report the nodes that didn't respond by it
+pub const REPORT_GRACE: Duration = Duration::from_millis(500);
+
+/// Limits the processing time of the `router`'s requests,
+/// responding with [`APIError::RequestTimeout`]
+///
+/// Every request has to be processed within the current request timeout. The requests to the nodes
+/// are cut off at that [deadline](crate::deadline), so the handlers report a partial outcome
+/// naming the nodes that timed out. Only the handlers that don't do so within [`REPORT_GRACE`]
+/// are cut off themselves
+pub fn with_request_timeout(router: Router, runtime: RuntimeConfig) -> Router {
+    router.layer(from_fn_with_state(runtime, limit_request_time))
+}
+
+async fn limit_request_time(
+    State(runtime): State<RuntimeConfig>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    let timeout = runtime.request_timeout();
+    let deadline = Instant::now() + timeout;
+    let mut next = Some(next);
+    let service = tower::service_fn(move |request| {
+        let next = next.take();
+        deadline::scope(deadline, async move {
+            Ok::<_, Infallible>(match next {
+                Some(next) => next.run(request).await,
+                None => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            })
+        })
+    });
+
+    match TimeoutLayer::new(timeout + REPORT_GRACE)
+        .layer(service)
+        .oneshot(request)
+        .await
+    {
+        Ok(response) => response,
+        Err(_) => APIError::RequestTimeout.into_response(),
+    }
+}
+
+/// Fallback of the unknown API paths
+pub async fn api_not_found(OriginalUri(uri): OriginalUri) -> Response {
+    ProblemDetails::from(APIError::NotFound)
+        .with_instance(uri.path())
+        .into_response()
 }
 
 /// Errors that happend during API request proccessing
-#[derive(Debug, Error)]
+#[derive(Debug, Error, PartialEq, Eq)]
 pub enum APIError {
     #[error("The request to the specified resource failed")]
     RequestFailed,
+    #[error("The request has invalid parameters")]
+    InvalidRequest,
+    #[error("Invalid credentials")]
+    InvalidCredentials,
+    #[error("No cluster specified")]
+    NoCluster,
+    #[error("Logging into a specified node isn't allowed")]
+    HostnameNotAllowed,
+    #[error("The cluster has been reconfigured since the login, log in again")]
+    ClusterChanged,
+    #[error("The requested resource doesn't exist")]
+    NotFound,
+    #[error("Not enough permissions to perform the operation")]
+    Forbidden,
+    #[error("The node couldn't be reached")]
+    NodeUnavailable,
+    #[error("The node responded with an error")]
+    NodeError,
+    #[error("Node `{0}` didn't respond in time")]
+    NodeTimeout(String),
+    #[error("The request took too long to process")]
+    RequestTimeout,
+    #[error("Invalid log filter directives or revert delay")]
+    InvalidLogFilter,
+    #[error("The request couldn't be processed")]
+    Internal,
     #[error("Server received invalid status code from client: `{0}`")]
     InvalidStatusCode(StatusCode),
 }
 
+impl APIError {
+    /// HTTP status of the error's response
+    #[must_use]
+    pub const fn status(&self) -> StatusCode {
+        match self {
+            Self::RequestFailed | Self::NotFound => StatusCode::NOT_FOUND,
+            Self::InvalidCredentials | Self::ClusterChanged => StatusCode::UNAUTHORIZED,
+            Self::InvalidRequest
+            | Self::NoCluster
+            | Self::HostnameNotAllowed
+            | Self::InvalidLogFilter => StatusCode::BAD_REQUEST,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::NodeUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Self::NodeError => StatusCode::BAD_GATEWAY,
+            Self::NodeTimeout(_) | Self::RequestTimeout => StatusCode::GATEWAY_TIMEOUT,
+            Self::InvalidStatusCode(code) => *code,
+            Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Stable code of the error, see [`ProblemDetails::code`]
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::RequestFailed => "request_failed",
+            Self::InvalidRequest => "invalid_request",
+            Self::InvalidCredentials => "invalid_credentials",
+            Self::NoCluster => "no_cluster",
+            Self::HostnameNotAllowed => "hostname_not_allowed",
+            Self::ClusterChanged => "cluster_changed",
+            Self::NotFound => "not_found",
+            Self::Forbidden => "forbidden",
+            Self::NodeUnavailable => "node_unavailable",
+            Self::NodeError => "node_error",
+            Self::NodeTimeout(_) => "node_timeout",
+            Self::RequestTimeout => "request_timeout",
+            Self::InvalidLogFilter => "invalid_log_filter",
+            Self::InvalidStatusCode(_) => "unexpected_status",
+            Self::Internal => "internal_error",
+        }
+    }
+
+    /// Converts the error of the request to the `node`, naming the node if it timed out
+    #[must_use]
+    pub fn from_node(node: &str, report: Report<ClientError>) -> Self {
+        if let ClientError::Timeout(_) = report.current_context() {
+            tracing::warn!("node {node}: {report:?}");
+            Self::NodeTimeout(node.to_string())
+        } else {
+            Self::from(report)
+        }
+    }
+
+    /// Problem of the failed request to the `node`.
+    /// The context gathered by the `report`, such as the node's address and response, is only logged
+    #[must_use]
+    pub fn node_problem(node: &str, report: Report<ClientError>) -> ProblemDetails {
+        ProblemDetails::from(Self::from_node(node, report)).with_node(node)
+    }
+}
+
+impl From<APIError> for ProblemDetails {
+    fn from(error: APIError) -> Self {
+        let problem = Self::new(error.status(), error.code()).with_detail(error.to_string());
+        match error {
+            APIError::NodeTimeout(node) => problem.with_node(node),
+            _ => problem,
+        }
+    }
+}
+
 impl IntoResponse for APIError {
     fn into_response(self) -> Response {
-        match self {
-            Self::RequestFailed => (StatusCode::NOT_FOUND, self.to_string()).into_response(),
-            Self::InvalidStatusCode(code) => code.into_response(),
+        ProblemDetails::from(self).into_response()
+    }
+}
+
+impl From<Report<ClientError>> for APIError {
+    fn from(report: Report<ClientError>) -> Self {
+        tracing::warn!("{report:?}");
+        match *report.current_context() {
+            // The user is already logged in, so the node rejecting the session's credentials
+            // means they aren't allowed to do this on the node
+            ClientError::UnexpectedStatus(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+                Self::Forbidden
+            }
+            ClientError::UnexpectedStatus(StatusCode::NOT_FOUND) => Self::NotFound,
+            ClientError::UnexpectedStatus(code) if code.is_client_error() => {
+                Self::InvalidStatusCode(code)
+            }
+            ClientError::UnexpectedStatus(_) | ClientError::InvalidResponse => Self::NodeError,
+            ClientError::InvalidAddress => Self::InvalidRequest,
+            ClientError::RequestFailed => Self::NodeUnavailable,
+            // The node's name isn't known here, so it's named by its address
+            ClientError::Timeout(ref address) => Self::NodeTimeout(address.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn client_errors_mapping() {
+        for (client_error, api_error) in [
+            (
+                ClientError::UnexpectedStatus(StatusCode::UNAUTHORIZED),
+                APIError::Forbidden,
+            ),
+            (
+                ClientError::UnexpectedStatus(StatusCode::FORBIDDEN),
+                APIError::Forbidden,
+            ),
+            (
+                ClientError::UnexpectedStatus(StatusCode::NOT_FOUND),
+                APIError::NotFound,
+            ),
+            (
+                ClientError::UnexpectedStatus(StatusCode::NOT_ACCEPTABLE),
+                APIError::InvalidStatusCode(StatusCode::NOT_ACCEPTABLE),
+            ),
+            (
+                ClientError::UnexpectedStatus(StatusCode::INTERNAL_SERVER_ERROR),
+                APIError::NodeError,
+            ),
+            (ClientError::InvalidResponse, APIError::NodeError),
+            (ClientError::InvalidAddress, APIError::InvalidRequest),
+            (ClientError::RequestFailed, APIError::NodeUnavailable),
+            (
+                ClientError::Timeout("127.0.0.1:20000".to_string()),
+                APIError::NodeTimeout("127.0.0.1:20000".to_string()),
+            ),
+        ] {
+            assert_eq!(APIError::from(Report::new(client_error)), api_error);
         }
+
+        assert_eq!(
+            APIError::from_node(
+                "node1",
+                Report::new(ClientError::Timeout("127.0.0.1:20000".to_string()))
+            ),
+            APIError::NodeTimeout("node1".to_string())
+        );
+        assert_eq!(
+            APIError::from_node("node1", Report::new(ClientError::RequestFailed)),
+            APIError::NodeUnavailable
+        );
+    }
+
+    #[tokio::test]
+    async fn request_timeout() {
+        use axum::routing::get;
+
+        let slow = || async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            "done"
+        };
+        let router = with_request_timeout(
+            Router::new().route("/slow", get(slow).post(slow)),
+            RuntimeConfig::new(&cli::Config {
+                request_timeout: Duration::from_millis(50),
+                ..cli::Config::default()
+            }),
+        );
+        for request in [hyper::Request::get("/slow"), hyper::Request::post("/slow")] {
+            let response = router
+                .clone()
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        }
+    }
+
+    #[tokio::test]
+    async fn node_requests_end_at_deadline() {
+        use crate::connector::{mock::spawn_hanging_node, BobClient};
+        use axum::routing::post;
+
+        let addr = spawn_hanging_node();
+        // The node is given more time than the whole request
+        let client = BobClient::try_new(&addr.to_string(), Duration::from_secs(10)).unwrap();
+        let stop = move || async move {
+            client
+                .stop_disk("disk1")
+                .await
+                .map_err(|err| APIError::from_node("node1", err))
+        };
+        let router = with_request_timeout(
+            Router::new().route("/stop", post(stop)),
+            RuntimeConfig::new(&cli::Config {
+                request_timeout: Duration::from_millis(50),
+                ..cli::Config::default()
+            }),
+        );
+
+        let response = router
+            .oneshot(hyper::Request::post("/stop").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let problem: ProblemDetails = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem.code, "node_timeout");
+        assert_eq!(
+            problem.detail,
+            Some(APIError::NodeTimeout("node1".to_string()).to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_api_path() {
+        let response = Router::new()
+            .nest("/api/v1", api_router_v1().unwrap())
+            .oneshot(
+                hyper::Request::get("/api/v1/unknown")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let problem: ProblemDetails = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem.code, "not_found");
+        assert_eq!(problem.instance.as_deref(), Some("/api/v1/unknown"));
     }
 }
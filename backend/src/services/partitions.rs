@@ -0,0 +1,398 @@
+use super::APIError;
+use crate::{
+    connector::{dto, BobClient, ClientError, ClusterClient},
+    models::api::{NodeStatus, Partition, PartitionsDeletion, ReplicaPartitions},
+    prelude::*,
+};
+use axum::{
+    extract::{Path, Query},
+    Extension, Json,
+};
+use futures::{future::join_all, stream::FuturesUnordered, StreamExt};
+use hyper::StatusCode;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+
+/// Query of the old partitions deletion
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct OlderThan {
+    /// Unix timestamp in seconds, partitions started before it are deleted
+    pub older_than: u64,
+}
+
+/// Lists partitions of the vdisk on every node that hosts its replica
+///
+/// # Errors
+///
+/// This function will return an error if the vdisk doesn't exist
+/// or none of the nodes could be reached
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        get,
+        context_path = "/api/v1",
+        path = "/vdisks/{vdisk_id}/partitions",
+        params(
+            ("vdisk_id" = u64, Path, description = "Id of the vdisk")
+        ),
+        responses(
+            (status = 200, body = [ReplicaPartitions], content_type = "application/json", description = "Partitions of the vdisk's replicas"),
+            (status = 404, body = ProblemDetails, content_type = "application/problem+json", description = "VDisk not found"),
+            (status = 503, body = ProblemDetails, content_type = "application/problem+json", description = "Cluster couldn't be reached"),
+            (status = 504, body = ProblemDetails, content_type = "application/problem+json", description = "Cluster didn't respond in time")
+        )
+    ))]
+pub async fn get_partitions(
+    Extension(cluster): Extension<ClusterClient>,
+    Path(vdisk_id): Path<u64>,
+) -> AxumResult<Json<Vec<ReplicaPartitions>>> {
+    let vdisk = find_vdisk(&cluster, vdisk_id).await?;
+    let replicas = join_all(replica_nodes(&vdisk).into_iter().map(|node| {
+        let client = cluster.node(node);
+        async move {
+            let (status, partitions) = match client {
+                Some(client) => {
+                    let partitions = fetch_partitions(client, vdisk_id).await;
+                    let status = NodeStatus::from_result(&partitions);
+                    (
+                        status,
+                        partitions
+                            .map_err(|err| tracing::warn!("node {node}: {err:?}"))
+                            .unwrap_or_default(),
+                    )
+                }
+                None => (NodeStatus::Offline, Vec::new()),
+            };
+            ReplicaPartitions {
+                node: node.to_string(),
+                status,
+                partitions,
+            }
+        }
+    }))
+    .await;
+
+    Ok(Json(replicas))
+}
+
+/// Deletes the partition of the vdisk's replica stored on the node
+///
+/// # Errors
+///
+/// This function will return an error if the node or the partition doesn't exist
+/// or the node rejected the request
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        delete,
+        context_path = "/api/v1",
+        path = "/nodes/{node_name}/vdisks/{vdisk_id}/partitions/{timestamp}",
+        params(
+            ("node_name" = String, Path, description = "Name of the node"),
+            ("vdisk_id" = u64, Path, description = "Id of the vdisk"),
+            ("timestamp" = u64, Path, description = "Timestamp of the partition")
+        ),
+        responses(
+            (status = 200, description = "Partition deleted"),
+            (status = 403, body = ProblemDetails, content_type = "application/problem+json", description = "Not enough permissions"),
+            (status = 404, body = ProblemDetails, content_type = "application/problem+json", description = "Node or partition not found"),
+            (status = 502, body = ProblemDetails, content_type = "application/problem+json", description = "Node failed to delete the partition"),
+            (status = 503, body = ProblemDetails, content_type = "application/problem+json", description = "Node couldn't be reached"),
+            (status = 504, body = ProblemDetails, content_type = "application/problem+json", description = "Node didn't respond in time")
+        )
+    ))]
+pub async fn delete_partition(
+    Extension(cluster): Extension<ClusterClient>,
+    Path((node_name, vdisk_id, timestamp)): Path<(String, u64, u64)>,
+) -> AxumResult<StatusCode> {
+    let client = cluster.node(&node_name).ok_or(APIError::NotFound)?;
+    client
+        .delete_partition(vdisk_id, timestamp)
+        .await
+        .map_err(|err| APIError::node_problem(&node_name, err))?;
+    tracing::info!("partition {timestamp} of vdisk {vdisk_id} deleted on {node_name}");
+
+    Ok(StatusCode::OK)
+}
+
+/// Deletes partitions of the vdisk started before `older_than` on every replica node
+///
+/// Partitions that couldn't be deleted and the replica nodes that couldn't be reached
+/// are reported instead of failing the whole request
+///
+/// # Errors
+///
+/// This function will return an error if the vdisk doesn't exist
+/// or none of the nodes could be reached
+#[cfg_attr(all(feature = "swagger", debug_assertions), utoipa::path(
+        delete,
+        context_path = "/api/v1",
+        path = "/vdisks/{vdisk_id}/partitions",
+        params(
+            ("vdisk_id" = u64, Path, description = "Id of the vdisk"),
+            ("older_than" = u64, Query, description = "Unix timestamp in seconds, partitions started before it are deleted")
+        ),
+        responses(
+            (status = 200, body = PartitionsDeletion, content_type = "application/json", description = "Deleted and failed partitions, unreached nodes"),
+            (status = 404, body = ProblemDetails, content_type = "application/problem+json", description = "VDisk not found"),
+            (status = 503, body = ProblemDetails, content_type = "application/problem+json", description = "Cluster couldn't be reached"),
+            (status = 504, body = ProblemDetails, content_type = "application/problem+json", description = "Cluster didn't respond in time")
+        )
+    ))]
+pub async fn delete_old_partitions(
+    Extension(cluster): Extension<ClusterClient>,
+    Path(vdisk_id): Path<u64>,
+    Query(OlderThan { older_than }): Query<OlderThan>,
+) -> AxumResult<Json<PartitionsDeletion>> {
+    let vdisk = find_vdisk(&cluster, vdisk_id).await?;
+    let results = join_all(replica_nodes(&vdisk).into_iter().map(|node| {
+        let client = cluster.node(node);
+        async move {
+            let client = client.ok_or_else(|| {
+                APIError::node_problem(
+                    node,
+                    Report::new(ClientError::RequestFailed)
+                        .attach_printable("the node isn't a part of the discovered cluster"),
+                )
+            })?;
+            let partitions = fetch_partitions(client, vdisk_id)
+                .await
+                .map_err(|err| APIError::node_problem(node, err))?;
+            let deletions = partitions
+                .into_iter()
+                .filter(|partition| partition.timestamp < older_than)
+                .map(|partition| async move {
+                    let result = client
+                        .delete_partition(vdisk_id, partition.timestamp)
+                        .await
+                        .map_err(|err| tracing::warn!("node {}: {err:?}", partition.node));
+                    (partition, result.is_ok())
+                });
+
+            Ok(join_all(deletions).await)
+        }
+    }))
+    .await;
+
+    let mut deletion = PartitionsDeletion::default();
+    for result in results {
+        match result {
+            Ok(partitions) => {
+                for (partition, deleted) in partitions {
+                    if deleted {
+                        deletion.deleted.push(partition);
+                    } else {
+                        deletion.failed.push(partition);
+                    }
+                }
+            }
+            Err(problem) => deletion.unreached.push(problem),
+        }
+    }
+    if !deletion.unreached.is_empty() {
+        tracing::warn!(
+            "{} replica nodes of vdisk {vdisk_id} couldn't be reached, their partitions are left",
+            deletion.unreached.len()
+        );
+    }
+    tracing::info!(
+        "{} partitions of vdisk {vdisk_id} older than {older_than} deleted",
+        deletion.deleted.len()
+    );
+
+    Ok(Json(deletion))
+}
+
+/// Requests the vdisk from every node of the cluster at once, until one of them responds
+///
+/// # Errors
+///
+/// This function will return [`APIError::NodeTimeout`] naming a node that timed out
+/// if none of the nodes responded, or [`APIError::NodeUnavailable`] if none timed out
+async fn find_vdisk(
+    cluster: &ClusterClient,
+    vdisk_id: u64,
+) -> std::result::Result<dto::VDisk, APIError> {
+    let mut requests: FuturesUnordered<_> = cluster
+        .nodes()
+        .map(|(name, client)| async move { (name, client.get_vdisk(vdisk_id).await) })
+        .collect();
+    let mut failure = APIError::NodeUnavailable;
+    while let Some((name, result)) = requests.next().await {
+        match result {
+            Ok(vdisk) => return Ok(vdisk),
+            Err(err) => match err.current_context() {
+                ClientError::UnexpectedStatus(StatusCode::NOT_FOUND) => {
+                    return Err(APIError::NotFound)
+                }
+                // A node that timed out is named, unless another one responded
+                ClientError::Timeout(_) => failure = APIError::from_node(name, err),
+                _ => tracing::warn!("node {name}: {err:?}"),
+            },
+        }
+    }
+
+    Err(failure)
+}
+
+/// Names of the nodes that host the vdisk's replicas, without duplicates
+fn replica_nodes(vdisk: &dto::VDisk) -> BTreeSet<&str> {
+    vdisk
+        .replicas
+        .iter()
+        .map(|replica| replica.node.as_str())
+        .collect()
+}
+
+/// Fetches the vdisk's partitions stored on the node, ordered by timestamp.
+/// Partitions the node failed to describe are skipped
+///
+/// # Errors
+///
+/// This function will return an error if the node couldn't list the partitions
+async fn fetch_partitions(
+    client: &BobClient,
+    vdisk_id: u64,
+) -> Result<Vec<Partition>, ClientError> {
+    let ids = client.get_partitions(vdisk_id).await?.partitions;
+    let mut partitions: Vec<_> = join_all(ids.iter().map(|id| client.get_partition(vdisk_id, id)))
+        .await
+        .into_iter()
+        .filter_map(|partition| {
+            partition
+                .map(Partition::from)
+                .map_err(|err| tracing::warn!("{err:?}"))
+                .ok()
+        })
+        .collect();
+    partitions.sort_by_key(|partition| partition.timestamp);
+
+    Ok(partitions)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::{
+        connector::mock::{client, offline_address, replica, send, spawn_hanging_node, MockNode},
+        error::ProblemDetails,
+    };
+    use axum::{
+        routing::{delete, get},
+        Router,
+    };
+    use hyper::Method;
+
+    /// `node1` hosts partitions 100 and 200 of vdisk 0, `node2` is offline
+    fn app() -> Router {
+        let mut node1 = MockNode::new("node1", &[0])
+            .with_partition(0, 200, 20)
+            .with_partition(0, 100, 10);
+        node1.status.vdisks[0]
+            .replicas
+            .push(replica("node2", "disk1", 0));
+        let node1 = client(&node1.spawn().to_string());
+        let cluster = ClusterClient::new(
+            node1.clone(),
+            [
+                ("node1".to_string(), node1),
+                ("node2".to_string(), client(&offline_address())),
+            ],
+        );
+
+        Router::new()
+            .route(
+                "/vdisks/:vdisk_id/partitions",
+                get(get_partitions).delete(delete_old_partitions),
+            )
+            .route(
+                "/nodes/:node_name/vdisks/:vdisk_id/partitions/:timestamp",
+                delete(delete_partition),
+            )
+            .layer(Extension(cluster))
+    }
+
+    #[tokio::test]
+    async fn list_partitions() {
+        let (status, body) = send(&app(), Method::GET, "/vdisks/0/partitions").await;
+        assert_eq!(status, StatusCode::OK);
+        let replicas: Vec<ReplicaPartitions> = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(replicas.len(), 2);
+        assert_eq!(replicas[0].status, NodeStatus::Online);
+        assert_eq!(
+            replicas[0]
+                .partitions
+                .iter()
+                .map(|partition| (partition.timestamp, partition.records_count))
+                .collect::<Vec<_>>(),
+            vec![(100, 10), (200, 20)]
+        );
+        assert_eq!(replicas[1].status, NodeStatus::Offline);
+        assert!(replicas[1].partitions.is_empty());
+
+        let (status, _) = send(&app(), Method::GET, "/vdisks/7/partitions").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn delete_single_partition() {
+        let app = app();
+
+        let (status, _) = send(&app, Method::DELETE, "/nodes/node1/vdisks/0/partitions/100").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, Method::DELETE, "/nodes/node1/vdisks/0/partitions/100").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send(&app, Method::DELETE, "/nodes/node3/vdisks/0/partitions/200").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn delete_partitions_by_timestamp() {
+        let app = app();
+
+        let (status, body) =
+            send(&app, Method::DELETE, "/vdisks/0/partitions?older_than=150").await;
+        assert_eq!(status, StatusCode::OK);
+        let deletion: PartitionsDeletion = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            deletion
+                .deleted
+                .iter()
+                .map(|partition| partition.timestamp)
+                .collect::<Vec<_>>(),
+            vec![100]
+        );
+        assert!(deletion.failed.is_empty());
+        assert_eq!(deletion.unreached.len(), 1);
+        assert_eq!(deletion.unreached[0].node.as_deref(), Some("node2"));
+        assert_eq!(deletion.unreached[0].code, "node_unavailable");
+
+        let (_, body) = send(&app, Method::GET, "/vdisks/0/partitions").await;
+        let replicas: Vec<ReplicaPartitions> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(replicas[0].partitions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn vdisk_lookup_timeout() {
+        let cluster = ClusterClient::new(
+            client(&offline_address()),
+            [
+                (
+                    "node1".to_string(),
+                    client(&spawn_hanging_node().to_string()),
+                ),
+                ("node2".to_string(), client(&offline_address())),
+            ],
+        );
+        let app = Router::new()
+            .route("/vdisks/:vdisk_id/partitions", get(get_partitions))
+            .layer(Extension(cluster));
+
+        let (status, body) = send(&app, Method::GET, "/vdisks/0/partitions").await;
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+        let problem: ProblemDetails = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem.code, "node_timeout");
+        assert_eq!(
+            problem.detail,
+            Some(APIError::NodeTimeout("node1".to_string()).to_string())
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Graceful shutdown of the server
+
+use std::{future::Future, time::Duration};
+use tokio::sync::oneshot;
+
+/// Completes once the process receives SIGINT or SIGTERM
+///
+/// # Panics
+///
+/// Panics if the signal handlers couldn't be installed
+#[allow(clippy::expect_used)]
+pub async fn shutdown_signal() {
+    let interrupt = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Couldn't install SIGINT handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Couldn't install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = interrupt => tracing::info!("SIGINT received"),
+        () = terminate => tracing::info!("SIGTERM received"),
+    }
+}
+
+/// Runs the server until `signal` completes, then waits up to `drain_timeout`
+/// for the in-flight requests to finish
+///
+/// `serve` receives the future to pass into the server's `with_graceful_shutdown`.
+/// Returns `false` if the requests were dropped because the timeout elapsed
+///
+/// # Errors
+///
+/// This function will return an error if the server fails
+pub async fn serve_until<S, E>(
+    serve: impl FnOnce(Box<dyn Future<Output = ()> + Send + Unpin>) -> S,
+    signal: impl Future<Output = ()> + Send,
+    drain_timeout: Duration,
+) -> Result<bool, E>
+where
+    S: Future<Output = Result<(), E>>,
+{
+    let (stop, stopped) = oneshot::channel::<()>();
+    let server = serve(Box::new(Box::pin(async {
+        let _ = stopped.await;
+    })));
+    tokio::pin!(server);
+
+    tokio::select! {
+        result = &mut server => result.map(|()| true),
+        () = signal => {
+            tracing::info!("Shutting down, waiting up to {drain_timeout:?} for in-flight requests");
+            let _ = stop.send(());
+            match tokio::time::timeout(drain_timeout, server).await {
+                Ok(result) => result.map(|()| true),
+                Err(_) => {
+                    tracing::warn!("In-flight requests didn't finish in {drain_timeout:?}, dropping them");
+                    Ok(false)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use axum::{routing::get, Router};
+    use hyper::{Client, StatusCode};
+    use std::net::SocketAddr;
+    use tokio::{sync::oneshot, task::JoinHandle};
+
+    /// Starts the server with a single `/sleep` route that responds after `delay`,
+    /// requests it and stops the server once the request is in flight
+    async fn shutdown_during_request(
+        delay: Duration,
+        drain_timeout: Duration,
+    ) -> (bool, JoinHandle<Option<StatusCode>>) {
+        let router = Router::new().route(
+            "/sleep",
+            get(move || async move {
+                tokio::time::sleep(delay).await;
+                "done"
+            }),
+        );
+        let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(router.into_make_service());
+        let addr = server.local_addr();
+        let (stop, signal) = oneshot::channel::<()>();
+
+        let request = tokio::spawn(async move {
+            let response = Client::new()
+                .get(format!("http://{addr}/sleep").parse().unwrap())
+                .await;
+            response.ok().map(|response| response.status())
+        });
+        let drained = tokio::spawn(serve_until(
+            |shutdown| server.with_graceful_shutdown(shutdown),
+            async {
+                let _ = signal.await;
+            },
+            drain_timeout,
+        ));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        stop.send(()).unwrap();
+
+        let drained = drained.await.unwrap().unwrap();
+        (drained, request)
+    }
+
+    #[tokio::test]
+    async fn drain_in_flight_requests() {
+        let (drained, status) =
+            shutdown_during_request(Duration::from_millis(100), Duration::from_secs(5)).await;
+
+        assert!(drained);
+        assert_eq!(status.await.unwrap(), Some(StatusCode::OK));
+    }
+
+    #[tokio::test]
+    async fn drain_timeout() {
+        let (drained, request) =
+            shutdown_during_request(Duration::from_secs(5), Duration::from_millis(100)).await;
+
+        assert!(!drained);
+        // Dropped connections are closed once the runtime stops
+        request.abort();
+    }
+}
@@ -0,0 +1,217 @@
+//! HTTPS termination
+
+use crate::prelude::*;
+use axum::{
+    extract::Host,
+    http::{uri::PathAndQuery, Uri},
+    response::Redirect,
+    Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use cli::TlsConfig;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Error)]
+pub enum TlsError {
+    #[error("Couldn't load TLS certificate or key")]
+    LoadCertificate,
+}
+
+/// Loads the certificate chain and the private key specified in the `config`
+///
+/// # Errors
+///
+/// This function will return an error if the files couldn't be read or contain invalid PEM data
+pub async fn load(config: &TlsConfig) -> Result<RustlsConfig, TlsError> {
+    RustlsConfig::from_pem_file(&config.cert, &config.key)
+        .await
+        .change_context(TlsError::LoadCertificate)
+        .attach_printable_lazy(|| describe(config))
+}
+
+/// Replaces the certificate served by `rustls` with the one currently stored in the files.
+/// The old certificate is kept if the new one is invalid
+///
+/// # Errors
+///
+/// This function will return an error if the files couldn't be read or contain invalid PEM data
+pub async fn reload(rustls: &RustlsConfig, config: &TlsConfig) -> Result<(), TlsError> {
+    rustls
+        .reload_from_pem_file(&config.cert, &config.key)
+        .await
+        .change_context(TlsError::LoadCertificate)
+        .attach_printable_lazy(|| describe(config))
+}
+
+/// Reloads the certificate every time the process receives SIGHUP
+///
+/// # Panics
+///
+/// Panics if the signal handler couldn't be installed
+#[cfg(unix)]
+#[must_use]
+#[allow(clippy::expect_used)]
+pub fn reload_on_sighup(rustls: RustlsConfig, config: TlsConfig) -> JoinHandle<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = signal(SignalKind::hangup()).expect("Couldn't install SIGHUP handler");
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            match reload(&rustls, &config).await {
+                Ok(()) => tracing::info!("TLS certificate reloaded"),
+                Err(err) => tracing::error!("Keeping the old TLS certificate: {err:?}"),
+            }
+        }
+    })
+}
+
+/// Router that redirects every request to the same path on HTTPS `port`
+pub fn redirect_router(port: u16) -> Router {
+    Router::new().fallback(move |Host(host): Host, uri: Uri| async move {
+        let host = host
+            .rsplit_once(':')
+            .filter(|(_, port)| port.parse::<u16>().is_ok())
+            .map_or(host.as_str(), |(host, _)| host);
+        let path = uri.path_and_query().map_or("/", PathAndQuery::as_str);
+
+        Redirect::permanent(&format!("https://{host}:{port}{path}"))
+    })
+}
+
+fn describe(config: &TlsConfig) -> String {
+    format!("cert: {:?}, key: {:?}", config.cert, config.key)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use axum::{http::header::LOCATION, routing::get};
+    use base64::Engine;
+    use hyper::{Body, Request, StatusCode};
+    use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+    use tokio::{io::AsyncWriteExt, net::TcpStream};
+    use tokio_rustls::{
+        rustls::{Certificate, ClientConfig, RootCertStore, ServerName},
+        TlsConnector,
+    };
+    use tower::ServiceExt;
+
+    /// Writes a new self-signed certificate for `localhost` and its key into the `dir`
+    fn generate_certificate(dir: &PathBuf) -> (TlsConfig, Certificate) {
+        let certificate =
+            rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        std::fs::create_dir_all(dir).unwrap();
+        let config = TlsConfig {
+            cert: dir.join("cert.pem"),
+            key: dir.join("key.pem"),
+            redirect_http: None,
+        };
+        // Every serialization signs the certificate anew, so PEM is built from the same DER
+        let der = certificate.serialize_der().unwrap();
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&der);
+        let lines: Vec<_> = encoded
+            .as_bytes()
+            .chunks(64)
+            .map(String::from_utf8_lossy)
+            .collect();
+        let pem = format!(
+            "-----BEGIN CERTIFICATE-----\n{}\n-----END CERTIFICATE-----\n",
+            lines.join("\n")
+        );
+        std::fs::write(&config.cert, pem).unwrap();
+        std::fs::write(&config.key, certificate.serialize_private_key_pem()).unwrap();
+
+        (config, Certificate(der))
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("bob-management-tls-{name}-{}", std::process::id()))
+    }
+
+    /// Returns the certificate the server presents during the handshake
+    async fn peer_certificate(addr: SocketAddr, trusted: &[&Certificate]) -> Certificate {
+        let mut roots = RootCertStore::empty();
+        for certificate in trusted {
+            roots.add(certificate).unwrap();
+        }
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut stream = TlsConnector::from(Arc::new(config))
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await
+            .unwrap();
+        let certificate = stream.get_ref().1.peer_certificates().unwrap()[0].clone();
+        stream.shutdown().await.unwrap();
+
+        certificate
+    }
+
+    #[tokio::test]
+    async fn serve_and_reload_certificate() {
+        let dir = temp_dir("reload");
+        let (config, first) = generate_certificate(&dir);
+        let rustls = load(&config).await.unwrap();
+
+        let handle = axum_server::Handle::new();
+        let server =
+            axum_server::bind_rustls(SocketAddr::from(([127, 0, 0, 1], 0)), rustls.clone())
+                .handle(handle.clone())
+                .serve(
+                    Router::new()
+                        .route("/", get(|| async { "Hello Bob!" }))
+                        .into_make_service(),
+                );
+        tokio::spawn(server);
+        let addr = handle.listening().await.unwrap();
+        assert_eq!(peer_certificate(addr, &[&first]).await, first);
+
+        let (_, second) = generate_certificate(&dir);
+        reload(&rustls, &config).await.unwrap();
+        assert_eq!(peer_certificate(addr, &[&first, &second]).await, second);
+
+        std::fs::write(&config.key, "invalid").unwrap();
+        assert!(reload(&rustls, &config).await.is_err());
+        assert_eq!(peer_certificate(addr, &[&first, &second]).await, second);
+
+        handle.shutdown();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn missing_certificate() {
+        let config = TlsConfig {
+            cert: temp_dir("missing").join("cert.pem"),
+            key: temp_dir("missing").join("key.pem"),
+            redirect_http: None,
+        };
+
+        assert!(matches!(
+            load(&config).await.unwrap_err().current_context(),
+            TlsError::LoadCertificate
+        ));
+    }
+
+    #[tokio::test]
+    async fn redirect_to_https() {
+        let response = redirect_router(9443)
+            .oneshot(
+                Request::get("/nodes/node1?tab=disks")
+                    .header("host", "bob.local:9080")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            response.headers()[LOCATION],
+            "https://bob.local:9443/nodes/node1?tab=disks"
+        );
+    }
+}
@@ -1,8 +1,15 @@
-use crate::config::{Config, FromFile};
-use clap::{crate_authors, crate_version, Parser};
-use error_stack::Report;
+use crate::{
+    config::{self, Config, DEFAULT_CONFIG},
+    format::{ConfigFormat, FieldPath},
+};
+use clap::{crate_authors, crate_version, ArgGroup, Parser, Subcommand};
 use error_stack::ResultExt;
-use std::path::PathBuf;
+use error_stack::{AttachmentKind, Frame, FrameKind, Report};
+use std::{
+    io::Write,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
 use thiserror::Error;
 
 lazy_static::lazy_static! {
@@ -20,12 +27,46 @@ lazy_static::lazy_static! {
     };
 }
 
-/// Bob configuration
+/// Bob Management GUI server
 #[derive(Debug, Parser, Clone)]
 #[command(author = crate_authors!())]
 #[command(version = VERSION.trim(), about, long_about)]
-#[group(id = "configs", required = true, multiple = false)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand, Clone)]
+pub enum Command {
+    /// Runs the server
+    Serve(ConfigArgs),
+
+    /// Checks the configuration file, printing every invalid value with its location.
+    /// Only the first error is reported if the file couldn't be parsed
+    CheckConfig {
+        /// Server configuration file
+        #[arg(short, long, value_name = "FILE")]
+        config_file: PathBuf,
+
+        /// Format of the configuration file, taken from its extension by default
+        #[arg(long, value_enum)]
+        config_format: Option<ConfigFormat>,
+    },
+
+    /// Prints the commented default configuration
+    PrintDefaultConfig,
+
+    /// Prints the configuration the server would run with, with the secrets redacted
+    PrintEffectiveConfig(ConfigArgs),
+}
+
+/// Bob configuration
+///
+/// Fields are taken from the defaults, then the configuration file,
+/// then the `BOBGUI_*` environment variables and then the flags, each overriding the previous ones
+#[derive(Debug, clap::Args, Clone)]
+#[command(group(ArgGroup::new("configs").args(["default", "config_file"]).required(true)))]
+pub struct ConfigArgs {
     /// If set, passes default configuration to the server
     #[clap(short, long)]
     default: bool,
@@ -33,18 +74,167 @@ pub struct Args {
     /// Server configuration file
     #[arg(short, long, value_name = "FILE")]
     config_file: Option<PathBuf>,
+
+    /// Format of the configuration file, taken from its extension by default
+    #[arg(long, value_enum, requires = "config_file")]
+    config_format: Option<ConfigFormat>,
+
+    /// Server address <host:port>, overriding the configured one
+    #[arg(short, long)]
+    address: Option<SocketAddr>,
 }
 
-impl TryFrom<Args> for Config {
+impl ConfigArgs {
+    /// Configuration file, if any
+    #[must_use]
+    pub fn config_file(&self) -> Option<&Path> {
+        self.config_file.as_deref()
+    }
+
+    /// Loads the configuration from the specified sources without validating it
+    ///
+    /// # Errors
+    ///
+    /// The function will fail if the file or the environment overrides couldn't be parsed
+    pub fn load(&self) -> Result<Config, Report<config::Error>> {
+        let mut config = Config::load(
+            self.config_file.clone(),
+            self.config_format,
+            std::env::vars_os(),
+        )?;
+        if let Some(address) = self.address {
+            config.address = address;
+        }
+
+        Ok(config)
+    }
+}
+
+impl TryFrom<ConfigArgs> for Config {
     type Error = Report<Error>;
 
-    fn try_from(value: Args) -> Result<Self, Self::Error> {
-        if value.default {
-            Ok(Self::default())
-        } else if let Some(config) = value.config_file {
-            Self::from_file(config).change_context(Error::Config)
-        } else {
-            unreachable!()
+    fn try_from(value: ConfigArgs) -> Result<Self, Self::Error> {
+        let config = value.load().change_context(Error::Config)?;
+        config.validate().change_context(Error::Config)?;
+
+        Ok(config)
+    }
+}
+
+impl Command {
+    /// Runs the configuration commands, writing their output to `out`.
+    /// Returns the configuration to serve with for the [`Serve`](Command::Serve) command
+    ///
+    /// # Errors
+    ///
+    /// The function will fail if the configuration is invalid or the output couldn't be written
+    pub fn run(self, out: &mut impl Write) -> Result<Option<Config>, Report<Error>> {
+        match self {
+            Self::Serve(args) => return Config::try_from(args).map(Some),
+            Self::CheckConfig {
+                config_file,
+                config_format,
+            } => {
+                let format = ConfigFormat::for_file(&config_file, config_format);
+                let parsed = std::fs::read_to_string(&config_file)
+                    .change_context(config::Error::FromFile)
+                    .attach_printable_lazy(|| config_file.display().to_string())
+                    .and_then(|text| Ok((format.parse::<Config>(&text)?, text)));
+                let problems = match parsed {
+                    Ok((config, text)) => config.validate().err().map_or_else(Vec::new, |report| {
+                        problems(&report, &|path| format.locate(&text, path))
+                    }),
+                    // Parsing stops at the first error, which carries its location itself
+                    Err(report) => problems(&report, &|_| None),
+                };
+                for problem in &problems {
+                    writeln!(out, "{}: {problem}", config_file.display())
+                        .change_context(Error::Output)?;
+                }
+                if !problems.is_empty() {
+                    return Err(Error::Config).attach_printable_lazy(|| {
+                        format!("{} errors in {}", problems.len(), config_file.display())
+                    });
+                }
+                writeln!(out, "{}: OK", config_file.display()).change_context(Error::Output)?;
+            }
+            Self::PrintDefaultConfig => {
+                write!(out, "{DEFAULT_CONFIG}").change_context(Error::Output)?;
+            }
+            Self::PrintEffectiveConfig(args) => {
+                let config = Config::try_from(args)?;
+                serde_yaml::to_writer(out, &config.redacted()).change_context(Error::Output)?;
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Descriptions of every error in the `report`, with the details attached to them
+/// and the location of the invalid field, found by `locate`
+fn problems(
+    report: &Report<config::Error>,
+    locate: &dyn Fn(&FieldPath) -> Option<(usize, usize)>,
+) -> Vec<String> {
+    let mut problems = vec![];
+    for frame in report.current_frames() {
+        describe(frame, Problem::default(), locate, &mut problems);
+    }
+
+    problems
+}
+
+/// Errors and details gathered down the frames of a single problem
+#[derive(Clone, Default)]
+struct Problem {
+    errors: Vec<String>,
+    details: Vec<String>,
+    path: FieldPath,
+}
+
+/// Adds the `frame` to the `problem`, describing it once the frame has no sources.
+/// Every source of the merged errors is a problem of its own
+fn describe(
+    frame: &Frame,
+    mut problem: Problem,
+    locate: &dyn Fn(&FieldPath) -> Option<(usize, usize)>,
+    problems: &mut Vec<String>,
+) {
+    match frame.kind() {
+        FrameKind::Context(context) => problem.errors.push(context.to_string()),
+        FrameKind::Attachment(AttachmentKind::Printable(detail)) => {
+            problem.details.push(detail.to_string());
+        }
+        FrameKind::Attachment(_) => {
+            if let Some(path) = frame.downcast_ref::<FieldPath>() {
+                problem.path = problem.path.join(path);
+            }
+        }
+    }
+
+    match frame.sources() {
+        [] => {
+            if let Some((line, column)) = locate(&problem.path) {
+                problem
+                    .details
+                    .push(format!("line {line}, column {column}"));
+            }
+            problems.push(if problem.details.is_empty() {
+                problem.errors.join(": ")
+            } else {
+                format!(
+                    "{} ({})",
+                    problem.errors.join(": "),
+                    problem.details.join(", ")
+                )
+            });
+        }
+        [source] => describe(source, problem, locate, problems),
+        sources => {
+            for source in sources {
+                describe(source, problem.clone(), locate, problems);
+            }
         }
     }
 }
@@ -55,4 +245,109 @@ pub enum Error {
     Logger,
     #[error("couldn't get server configuration")]
     Config,
+    #[error("couldn't write the output")]
+    Output,
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn check(yaml: &str) -> (bool, String) {
+        let path = std::env::temp_dir().join(format!(
+            "bob-management-check-{}-{}.yaml",
+            std::process::id(),
+            yaml.len()
+        ));
+        std::fs::write(&path, yaml).unwrap();
+        let mut out = vec![];
+        let result = Command::CheckConfig {
+            config_file: path.clone(),
+            config_format: None,
+        }
+        .run(&mut out);
+        std::fs::remove_file(path).unwrap();
+
+        (result.is_ok(), String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn check_config() {
+        let (ok, out) = check("address: 0.0.0.0:9000\n");
+        assert!(ok);
+        assert!(out.ends_with(": OK\n"), "{out}");
+
+        let (ok, out) = check("address: 0.0.0.0:9000\nmetrics:\n  samples: many\n");
+        assert!(!ok);
+        assert!(out.contains("couldn't parse YAML file"), "{out}");
+        assert!(out.contains("metrics.samples: invalid type"), "{out}");
+        assert!(out.contains("line 3, column 12"), "{out}");
+
+        let (ok, out) = check(
+            "
+address: 0.0.0.0:9000
+metrics: {samples: 1}
+cluster:
+  nodes:
+    - address: node1
+    - {address: 'node2:0', grpc-port: 0}
+",
+        );
+        assert!(!ok);
+        let problems: Vec<_> = out.lines().collect();
+        assert_eq!(problems.len(), 4, "{out}");
+        assert!(
+            problems[0].ends_with("(metrics.samples: 1, line 3, column 20)"),
+            "{out}"
+        );
+        assert!(
+            problems[1].ends_with("(cluster.nodes[0], address: node1, line 6, column 16)"),
+            "{out}"
+        );
+        assert!(
+            problems[2].ends_with("(cluster.nodes[1], address: node2:0, line 7, column 17)"),
+            "{out}"
+        );
+        assert!(
+            problems[3].ends_with("(cluster.nodes[1], grpc-port: 0, line 7, column 39)"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn effective_config() {
+        let path = std::env::temp_dir().join(format!(
+            "bob-management-effective-{}.yaml",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            "
+address: 0.0.0.0:9000
+cluster:
+  nodes: [{address: 'node1:8000'}]
+  credentials: {login: admin, password: password}
+",
+        )
+        .unwrap();
+        let args = Args::parse_from([
+            "bob-management",
+            "print-effective-config",
+            "--config-file",
+            path.to_str().unwrap(),
+            "--address",
+            "127.0.0.1:9100",
+        ]);
+        let mut out = vec![];
+        let config = args.command.run(&mut out).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert!(config.is_none());
+        let printed: Config = serde_yaml::from_slice(&out).unwrap();
+        assert_eq!(printed.address, SocketAddr::from(([127, 0, 0, 1], 9100)));
+        let cluster = printed.cluster.unwrap();
+        assert_eq!(cluster.credentials.unwrap().password, config::REDACTED);
+        assert_eq!(cluster.nodes[0].address, "node1:8000");
+    }
 }
@@ -1,33 +1,195 @@
-use error_stack::{Result, ResultExt};
-use serde::{de::DeserializeOwned, Deserialize};
-use serde_with::{serde_as, DisplayFromStr};
-use std::{fs::File, io::BufReader, net::SocketAddr, path::PathBuf, time::Duration};
+use crate::{
+    env,
+    format::{ConfigFormat, FieldPath},
+};
+use error_stack::{Report, Result, ResultExt};
+use serde::{
+    de::{self, value::MapAccessDeserializer, DeserializeOwned, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use serde_with::serde_as;
+use serde_yaml::{Mapping, Value};
+use std::{ffi::OsString, net::SocketAddr, path::PathBuf, time::Duration};
 use thiserror::Error;
 
+/// Placeholder of the secrets in the printed configuration
+pub const REDACTED: &str = "*****";
+
+/// Commented default configuration
+pub const DEFAULT_CONFIG: &str = include_str!("default.yaml");
+
 /// Server Configuration passed on initialization
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Config {
     /// Server address <host:port>
     pub address: SocketAddr,
 
-    /// Enable Default Cors configuration
-    #[serde(default = "Config::default_cors")]
-    pub cors_allow_all: bool,
+    /// [`CORS`](CorsConfig) Configuration. Plain `cors-allow-all: true` is accepted as well
+    #[serde(
+        default,
+        alias = "cors-allow-all",
+        deserialize_with = "CorsConfig::deserialize_compat"
+    )]
+    pub cors: CorsConfig,
 
-    /// Max Time to Responce, in milliseconds
+    /// [`Authentication`](AuthConfig) Configuration
+    #[serde(default)]
+    pub auth: AuthConfig,
+
+    /// Time given to an API request, the Bob nodes not responding within it are reported as timed out
     #[serde(default = "Config::default_timeout")]
     #[serde(with = "humantime_serde")]
     pub request_timeout: Duration,
 
+    /// Time given to the in-flight requests to complete on shutdown
+    #[serde(default = "Config::default_shutdown_timeout")]
+    #[serde(with = "humantime_serde")]
+    pub shutdown_timeout: Duration,
+
     /// [`Logger`](LoggerConfig) Configuration
     #[serde(default)]
     pub logger: LoggerConfig,
+
+    /// [`Cluster`](ClusterConfig) Connection Configuration
+    #[serde(default)]
+    pub cluster: Option<ClusterConfig>,
+
+    /// [`Metrics`](MetricsConfig) Scraping Configuration
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+
+    /// [`TLS`](TlsConfig) Configuration, plain HTTP is served if absent
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+}
+
+/// CORS Configuration
+///
+/// No CORS headers are sent unless some origins are allowed
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CorsConfig {
+    /// Allow any origin, method and header, with credentials. The other fields are ignored then
+    #[serde(default)]
+    pub allow_all: bool,
+
+    /// Allowed origins: exact ones, e.g. `https://bob.example.com`, or patterns where `*` matches
+    /// any part of the origin, e.g. `https://*.example.com`. Single `*` allows any origin
+    #[serde(default)]
+    pub origins: Vec<String>,
+
+    /// Allowed methods of the requests, `*` allows any method
+    #[serde(default = "CorsConfig::default_methods")]
+    pub methods: Vec<String>,
+
+    /// Allowed headers of the requests, `*` allows any header
+    #[serde(default = "CorsConfig::default_headers")]
+    pub headers: Vec<String>,
+
+    /// Allow requests with credentials, such as the session cookie
+    #[serde(default)]
+    pub credentials: bool,
+
+    /// Time the browsers may cache the preflight responses for
+    #[serde(default)]
+    #[serde(with = "humantime_serde")]
+    pub max_age: Option<Duration>,
+}
+
+/// Authentication Configuration
+///
+/// Users log in with the credentials of the Bob's users
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AuthConfig {
+    /// Allow users to log into any node they specify, if no cluster is configured.
+    /// The server connects to the user-supplied address then
+    #[serde(default)]
+    pub allow_hostname: bool,
+
+    /// Logins of the Bob's users allowed to use the admin endpoints, such as changing
+    /// the log filters. Nobody is allowed if empty
+    #[serde(default)]
+    pub admins: Vec<String>,
+}
+
+/// TLS Configuration
+///
+/// Certificates are reloaded from the same files on SIGHUP
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TlsConfig {
+    /// Certificate chain file in PEM format
+    pub cert: PathBuf,
+
+    /// Private key file in PEM format
+    pub key: PathBuf,
+
+    /// Address <host:port> of the listener that redirects HTTP requests to HTTPS
+    #[serde(default)]
+    pub redirect_http: Option<SocketAddr>,
+}
+
+/// Metrics Scraping Configuration
+///
+/// Nodes of the configured cluster are scraped in the background
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MetricsConfig {
+    /// Time between the scrapes of every node.
+    /// Nodes not scraped for twice as long are left out of the cluster metrics
+    #[serde(default = "MetricsConfig::default_scrape_interval")]
+    #[serde(with = "humantime_serde")]
+    pub scrape_interval: Duration,
+
+    /// Number of the latest samples kept for every node
+    #[serde(default = "MetricsConfig::default_samples")]
+    pub samples: usize,
+}
+
+/// Bob Cluster Connection Configuration
+///
+/// The rest of the cluster is discovered from the bootstrap nodes
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ClusterConfig {
+    /// Bootstrap nodes, tried in the specified order
+    pub nodes: Vec<BootstrapNode>,
+
+    /// Basic-auth credentials of the Bob's user the metrics are scraped with.
+    /// Users log in and query the cluster with their own credentials
+    #[serde(default)]
+    pub credentials: Option<ClusterCredentials>,
+
+    /// Authorization token the metrics are scraped with, mutually exclusive with `credentials`
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Bob node to discover the cluster from
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BootstrapNode {
+    /// Node's REST API address <host:port>
+    pub address: String,
+
+    /// Node's gRPC port, unused as the node is queried over its REST API
+    #[serde(default)]
+    pub grpc_port: Option<u16>,
+}
+
+/// Basic-auth credentials of the Bob's user
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ClusterCredentials {
+    pub login: String,
+
+    pub password: String,
 }
 
 /// Logger Configuration passed on initialization
-#[serde_as]
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct LoggerConfig {
     /// Rolling file logger config
@@ -38,15 +200,16 @@ pub struct LoggerConfig {
     #[serde(default)]
     pub stdout: Option<StdoutLogger>,
 
-    /// Tracing Level
-    #[serde(default = "LoggerConfig::level_default")]
-    #[serde_as(as = "DisplayFromStr")]
-    pub trace_level: tracing::Level,
+    /// [`EnvFilter`](https://docs.rs/tracing-subscriber/latest/tracing_subscriber/filter/struct.EnvFilter.html)
+    /// directives of the outputs without their own ones, e.g. `info,hyper=warn`.
+    /// Replaced by `RUST_LOG` if it's set. Plain `trace-level` is accepted as well
+    #[serde(default = "LoggerConfig::default_filter", alias = "trace-level")]
+    pub filter: String,
 }
 
 /// File Logger Configuration for writing logs to files
 #[serde_as]
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct FileLogger {
     /// Enable log output to file
@@ -62,24 +225,80 @@ pub struct FileLogger {
     /// Max size of a single log file, in bytes
     #[serde(default = "FileLogger::default_log_size")]
     pub log_size: usize,
+
+    /// Format of the log lines
+    #[serde(default)]
+    pub format: LogFormat,
+
+    /// Filter directives of this output, overriding the logger's ones
+    #[serde(default)]
+    pub filter: Option<String>,
 }
 
 /// Stdout Logger Configuration for printing logs into stdout
 #[serde_as]
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct StdoutLogger {
     /// Enable log output to stdout
     pub enabled: bool,
+
+    /// Format of the log lines
+    #[serde(default)]
+    pub format: LogFormat,
+
+    /// Filter directives of this output, overriding the logger's ones
+    #[serde(default)]
+    pub filter: Option<String>,
+}
+
+/// Format of the log lines
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// JSON object per line, with the target module and the fields of the current spans
+    Json,
+    /// Shorter human-readable lines
+    Compact,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             address: SocketAddr::from(([0, 0, 0, 0], 7000)),
-            cors_allow_all: Self::default_cors(),
+            cors: CorsConfig::default(),
+            auth: AuthConfig::default(),
             request_timeout: Self::default_timeout(),
+            shutdown_timeout: Self::default_shutdown_timeout(),
             logger: LoggerConfig::default(),
+            cluster: None,
+            metrics: MetricsConfig::default(),
+            tls: None,
+        }
+    }
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allow_all: false,
+            origins: vec![],
+            methods: Self::default_methods(),
+            headers: Self::default_headers(),
+            credentials: false,
+            max_age: None,
+        }
+    }
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            scrape_interval: Self::default_scrape_interval(),
+            samples: Self::default_samples(),
         }
     }
 }
@@ -89,27 +308,441 @@ impl Default for LoggerConfig {
         Self {
             file: None,
             stdout: None,
-            trace_level: Self::level_default(),
+            filter: Self::default_filter(),
         }
     }
 }
 
 impl Config {
     #[must_use]
-    pub const fn default_cors() -> bool {
-        false
+    pub const fn default_timeout() -> Duration {
+        Duration::from_millis(5000)
     }
 
     #[must_use]
-    pub const fn default_timeout() -> Duration {
-        Duration::from_millis(5000)
+    pub const fn default_shutdown_timeout() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    /// Reads the configuration from the file, or takes the default one if there is no file,
+    /// and applies the [overrides](crate::env) from the environment `vars` on top of it.
+    /// The file is parsed in the `format`, if specified, or the one of its extension
+    ///
+    /// # Errors
+    ///
+    /// The function will fail if the file couldn't be parsed,
+    /// the variables are invalid or their values don't fit the configuration
+    pub fn load(
+        file: Option<PathBuf>,
+        format: Option<ConfigFormat>,
+        vars: impl IntoIterator<Item = (OsString, OsString)>,
+    ) -> Result<Self, Error> {
+        let overrides = env::overrides(vars)?;
+        let mut config = match file {
+            Some(file) if overrides.is_empty() => return Self::from_file_as(file, format),
+            Some(file) => Value::from_file_as(file, format)?,
+            None => serde_yaml::to_value(Self::default()).change_context(Error::InvalidEnv)?,
+        };
+        env::apply(&mut config, &overrides, &Self::template());
+
+        // Plain YAML scalars fit both string and numeric fields, unlike the parsed values
+        serde_yaml::to_string(&config)
+            .and_then(|config| serde_yaml::from_str(&config))
+            .change_context(Error::InvalidEnv)
+            .attach_printable_lazy(|| {
+                let vars: Vec<_> = overrides.iter().map(|o| o.var.as_str()).collect();
+                format!("overridden by {}", vars.join(", "))
+            })
+    }
+
+    /// Configuration with every optional field set, showing the types of the fields
+    /// to the [overrides](crate::env)
+    fn template() -> Value {
+        let config = Self {
+            cors: CorsConfig {
+                max_age: Some(Duration::ZERO),
+                ..CorsConfig::default()
+            },
+            logger: LoggerConfig {
+                file: Some(FileLogger {
+                    enabled: FileLogger::default_enabled(),
+                    log_file: Some(PathBuf::new()),
+                    log_amount: FileLogger::default_log_amount(),
+                    log_size: FileLogger::default_log_size(),
+                    format: LogFormat::default(),
+                    filter: Some(String::new()),
+                }),
+                stdout: Some(StdoutLogger {
+                    enabled: StdoutLogger::default_enabled(),
+                    format: LogFormat::default(),
+                    filter: Some(String::new()),
+                }),
+                ..LoggerConfig::default()
+            },
+            cluster: Some(ClusterConfig {
+                nodes: vec![],
+                credentials: Some(ClusterCredentials {
+                    login: String::new(),
+                    password: String::new(),
+                }),
+                token: Some(String::new()),
+            }),
+            tls: Some(TlsConfig {
+                cert: PathBuf::new(),
+                key: PathBuf::new(),
+                redirect_http: Some(SocketAddr::from(([0, 0, 0, 0], 0))),
+            }),
+            ..Self::default()
+        };
+
+        serde_yaml::to_value(config).unwrap_or_default()
+    }
+
+    /// Checks the values that couldn't be checked on deserialization
+    ///
+    /// # Errors
+    ///
+    /// The function will fail if cluster or metrics configuration is invalid
+    pub fn validate(&self) -> Result<(), Error> {
+        merge([
+            self.cors.validate(),
+            self.metrics.validate(),
+            self.cluster
+                .as_ref()
+                .map_or(Ok(()), ClusterConfig::validate),
+        ])
+    }
+
+    /// Copy of the configuration with the secrets replaced by [`REDACTED`]
+    #[must_use]
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        if let Some(cluster) = &mut config.cluster {
+            if let Some(credentials) = &mut cluster.credentials {
+                credentials.password = REDACTED.to_string();
+            }
+            if let Some(token) = &mut cluster.token {
+                *token = REDACTED.to_string();
+            }
+        }
+
+        config
+    }
+
+    /// Fields that differ in the `other` configuration, with the secrets redacted
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> Vec<FieldChange> {
+        let value = |config: &Self| serde_yaml::to_value(config).unwrap_or_default();
+        let mut changes = vec![];
+        diff(
+            String::new(),
+            [&value(self), &value(other)],
+            [&value(&self.redacted()), &value(&other.redacted())],
+            &mut changes,
+        );
+
+        changes
+    }
+}
+
+/// Change of a configuration field
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldChange {
+    /// Path of the field, e.g. `logger.filter`
+    pub field: String,
+
+    pub old: String,
+
+    pub new: String,
+}
+
+impl std::fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} -> {}", self.field, self.old, self.new)
+    }
+}
+
+/// Collects the changes between the `values` under the `path`,
+/// described with the `redacted` values at the same paths
+fn diff(path: String, values: [&Value; 2], redacted: [&Value; 2], changes: &mut Vec<FieldChange>) {
+    if values[0] == values[1] {
+        return;
+    }
+    if let (
+        [Value::Mapping(old), Value::Mapping(new)],
+        [Value::Mapping(old_redacted), Value::Mapping(new_redacted)],
+    ) = (values, redacted)
+    {
+        let keys = old
+            .keys()
+            .chain(new.keys().filter(|key| !old.contains_key(*key)));
+        for key in keys {
+            let field = match (path.as_str(), key.as_str()) {
+                ("", Some(key)) => key.to_string(),
+                (_, Some(key)) => format!("{path}.{key}"),
+                (_, None) => continue,
+            };
+            let get = |mapping: &Mapping| mapping.get(key).unwrap_or(&Value::Null).clone();
+            diff(
+                field,
+                [&get(old), &get(new)],
+                [&get(old_redacted), &get(new_redacted)],
+                changes,
+            );
+        }
+        return;
+    }
+
+    let describe = |value: &Value| serde_json::to_string(value).unwrap_or_default();
+    changes.push(FieldChange {
+        field: path,
+        old: describe(redacted[0]),
+        new: describe(redacted[1]),
+    });
+}
+
+impl CorsConfig {
+    #[must_use]
+    pub fn default_methods() -> Vec<String> {
+        ["GET", "POST", "PUT", "PATCH", "DELETE"]
+            .map(ToString::to_string)
+            .to_vec()
+    }
+
+    #[must_use]
+    pub fn default_headers() -> Vec<String> {
+        ["content-type", "x-request-id"]
+            .map(ToString::to_string)
+            .to_vec()
+    }
+
+    /// Deserializes either the section or the boolean of the former `cors-allow-all` field
+    ///
+    /// # Errors
+    ///
+    /// The function will fail if the value is neither a boolean nor a valid section
+    pub fn deserialize_compat<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        struct CompatVisitor;
+
+        impl<'de> Visitor<'de> for CompatVisitor {
+            type Value = CorsConfig;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "CORS section or boolean")
+            }
+
+            fn visit_bool<E: de::Error>(
+                self,
+                allow_all: bool,
+            ) -> std::result::Result<Self::Value, E> {
+                Ok(CorsConfig {
+                    allow_all,
+                    ..CorsConfig::default()
+                })
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> std::result::Result<Self::Value, A::Error> {
+                CorsConfig::deserialize(MapAccessDeserializer::new(map))
+            }
+        }
+
+        deserializer.deserialize_any(CompatVisitor)
+    }
+
+    /// # Errors
+    ///
+    /// The function will fail if an origin isn't `<scheme>://<host>[:port]` pattern
+    /// or a method or header isn't a valid token
+    pub fn validate(&self) -> Result<(), Error> {
+        let origins = self.origins.iter().enumerate().map(|(index, origin)| {
+            let valid = origin == "*"
+                || (origin.chars().all(|c| c.is_ascii_graphic())
+                    && origin.split_once("://").is_some_and(|(scheme, host)| {
+                        !scheme.is_empty() && !host.is_empty() && !host.contains('/')
+                    }));
+            if valid {
+                Ok(())
+            } else {
+                Err(invalid_field(
+                    Error::InvalidCors,
+                    &format!("cors.origins[{index}]"),
+                    origin,
+                ))
+            }
+        });
+        let tokens = [("methods", &self.methods), ("headers", &self.headers)]
+            .into_iter()
+            .flat_map(|(field, tokens)| {
+                tokens.iter().enumerate().map(move |(index, token)| {
+                    if is_token(token) {
+                        Ok(())
+                    } else {
+                        Err(invalid_field(
+                            Error::InvalidCors,
+                            &format!("cors.{field}[{index}]"),
+                            token,
+                        ))
+                    }
+                })
+            });
+
+        merge(origins.chain(tokens))
+    }
+}
+
+/// Whether the `value` is an HTTP token, like the methods and header names are
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+}
+
+impl MetricsConfig {
+    #[must_use]
+    pub const fn default_scrape_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    #[must_use]
+    pub const fn default_samples() -> usize {
+        60
+    }
+
+    /// # Errors
+    ///
+    /// The function will fail if the scrape interval is zero
+    /// or less than two samples are kept, as rates couldn't be computed then
+    pub fn validate(&self) -> Result<(), Error> {
+        let scrape_interval = if self.scrape_interval.is_zero() {
+            Err(invalid_field(
+                Error::InvalidMetrics,
+                "metrics.scrape-interval",
+                0,
+            ))
+        } else {
+            Ok(())
+        };
+        let samples = if self.samples < 2 {
+            Err(invalid_field(
+                Error::InvalidMetrics,
+                "metrics.samples",
+                self.samples,
+            ))
+        } else {
+            Ok(())
+        };
+
+        merge([scrape_interval, samples])
+    }
+}
+
+impl ClusterConfig {
+    /// # Errors
+    ///
+    /// The function will fail if there are no bootstrap nodes, one of them has invalid address
+    /// or both credentials and token are specified
+    pub fn validate(&self) -> Result<(), Error> {
+        let nodes = if self.nodes.is_empty() {
+            Err(Error::NoBootstrapNodes.into())
+        } else {
+            Ok(())
+        };
+        let authorization = if self.credentials.is_some() && self.token.is_some() {
+            Err(Error::ConflictingAuthorization.into())
+        } else {
+            Ok(())
+        };
+
+        merge(
+            [nodes, authorization]
+                .into_iter()
+                .chain(self.nodes.iter().enumerate().map(|(index, node)| {
+                    let path = format!("cluster.nodes[{index}]");
+                    node.validate()
+                        .attach(FieldPath::from(path.as_str()))
+                        .attach_printable(path)
+                })),
+        )
+    }
+}
+
+impl BootstrapNode {
+    /// # Errors
+    ///
+    /// The function will fail if the address is not a <host:port> pair or gRPC port is zero
+    pub fn validate(&self) -> Result<(), Error> {
+        let valid = self.address.rsplit_once(':').is_some_and(|(host, port)| {
+            !host.is_empty() && port.parse::<u16>().is_ok_and(|port| port != 0)
+        });
+        let address = if valid {
+            Ok(())
+        } else {
+            Err(invalid_field(
+                Error::InvalidNodeAddress,
+                "address",
+                &self.address,
+            ))
+        };
+        let grpc_port = if self.grpc_port == Some(0) {
+            Err(invalid_field(Error::InvalidNodeAddress, "grpc-port", 0))
+        } else {
+            Ok(())
+        };
+
+        merge([address, grpc_port])
+    }
+}
+
+/// Error of the field at the `path`, attached with its value.
+/// The path is attached as [`FieldPath`] too, so that the field could be located in the file
+fn invalid_field(error: Error, path: &str, value: impl std::fmt::Display) -> Report<Error> {
+    Report::new(error)
+        .attach(FieldPath::from(path))
+        .attach_printable(format!("{path}: {value}"))
+}
+
+/// Combines the errors of the `results`, so that all of them are reported at once
+fn merge(results: impl IntoIterator<Item = Result<(), Error>>) -> Result<(), Error> {
+    let mut merged: Option<Report<Error>> = None;
+    for error in results.into_iter().filter_map(std::result::Result::err) {
+        match &mut merged {
+            Some(report) => report.extend_one(error),
+            None => merged = Some(error),
+        }
+    }
+
+    merged.map_or(Ok(()), Err)
+}
+
+impl std::fmt::Debug for ClusterConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClusterConfig")
+            .field("nodes", &self.nodes)
+            .field("credentials", &self.credentials)
+            .field("token", &self.token.as_ref().map(|_| REDACTED))
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for ClusterCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClusterCredentials")
+            .field("login", &self.login)
+            .field("password", &REDACTED)
+            .finish()
     }
 }
 
 impl LoggerConfig {
     #[must_use]
-    pub const fn level_default() -> tracing::Level {
-        tracing::Level::TRACE
+    pub fn default_filter() -> String {
+        "info".to_string()
     }
 }
 
@@ -144,6 +777,8 @@ impl Default for FileLogger {
             log_amount: Self::default_log_amount(),
             log_size: Self::default_log_size(),
             enabled: Self::default_enabled(),
+            format: LogFormat::default(),
+            filter: None,
         }
     }
 }
@@ -152,11 +787,14 @@ impl Default for StdoutLogger {
     fn default() -> Self {
         Self {
             enabled: Self::default_enabled(),
+            format: LogFormat::default(),
+            filter: None,
         }
     }
 }
+
 pub trait FromFile {
-    /// Parses the file spcified in `path`
+    /// Parses the file spcified in `path`, in the format of its extension or YAML
     ///
     /// # Errors
     ///
@@ -166,9 +804,25 @@ pub trait FromFile {
     where
         Self: Sized + DeserializeOwned,
     {
-        let file = File::open(path).change_context(Error::FromFile)?;
-        let reader = BufReader::new(file);
-        serde_yaml::from_reader(reader).change_context(Error::FromFile)
+        Self::from_file_as(path, None)
+    }
+
+    /// Parses the file spcified in `path` in the `format`,
+    /// or the one of its extension if it's not specified
+    ///
+    /// # Errors
+    ///
+    /// The fucntion will fail if either it couldn't read config file
+    /// or failed to parse given file
+    fn from_file_as(path: PathBuf, format: Option<ConfigFormat>) -> Result<Self, Error>
+    where
+        Self: Sized + DeserializeOwned,
+    {
+        let format = ConfigFormat::for_file(&path, format);
+        let text = std::fs::read_to_string(&path)
+            .change_context(Error::FromFile)
+            .attach_printable_lazy(|| path.display().to_string())?;
+        format.parse(&text)
     }
 }
 
@@ -176,6 +830,416 @@ pub trait FromFile {
 pub enum Error {
     #[error("configuration error: couldn't read from file")]
     FromFile,
+    #[error("configuration error: couldn't parse {0} file")]
+    Parse(ConfigFormat),
+    #[error("configuration error: invalid environment override")]
+    InvalidEnv,
+    #[error("configuration error: no cluster nodes specified")]
+    NoBootstrapNodes,
+    #[error("configuration error: invalid cluster node address, expected <host:port>")]
+    InvalidNodeAddress,
+    #[error("configuration error: cluster credentials and token are mutually exclusive")]
+    ConflictingAuthorization,
+    #[error("configuration error: metrics scrape interval must be positive and at least two samples kept")]
+    InvalidMetrics,
+    #[error("configuration error: invalid CORS origin, method or header")]
+    InvalidCors,
 }
 
 impl FromFile for Config {}
+
+impl FromFile for Value {}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn config(yaml: &str) -> Config {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn valid_cluster() {
+        let config = config(
+            "
+address: 0.0.0.0:9000
+cluster:
+  nodes:
+    - address: node1.bob:8000
+      grpc-port: 20000
+    - address: 127.0.0.1:8000
+  credentials:
+    login: admin
+    password: password
+",
+        );
+
+        assert!(config.validate().is_ok());
+        let cluster = config.cluster.unwrap();
+        assert_eq!(cluster.nodes[0].grpc_port, Some(20000));
+        assert_eq!(cluster.nodes[1].grpc_port, None);
+    }
+
+    #[test]
+    fn secrets_are_redacted_in_debug() {
+        let debug = format!(
+            "{:?}",
+            config(
+                "
+address: 0.0.0.0:9000
+cluster:
+  nodes:
+    - address: node1.bob:8000
+  credentials:
+    login: admin
+    password: secret-password
+"
+            )
+        );
+        assert!(!debug.contains("secret-password"), "{debug}");
+        assert!(debug.contains(REDACTED), "{debug}");
+
+        let debug = format!(
+            "{:?}",
+            config(
+                "
+address: 0.0.0.0:9000
+cluster:
+  nodes:
+    - address: node1.bob:8000
+  token: secret-token
+"
+            )
+        );
+        assert!(!debug.contains("secret-token"), "{debug}");
+        assert!(
+            debug.contains(&format!("token: Some({REDACTED:?})")),
+            "{debug}"
+        );
+    }
+
+    #[test]
+    fn no_cluster_nodes() {
+        let config = config("{address: 0.0.0.0:9000, cluster: {nodes: []}}");
+
+        assert!(matches!(
+            config.validate().unwrap_err().current_context(),
+            Error::NoBootstrapNodes
+        ));
+    }
+
+    #[test]
+    fn invalid_node_address() {
+        for address in ["node1", ":8000", "node1:port", "node1:0"] {
+            let config = config(&format!(
+                "{{address: 0.0.0.0:9000, cluster: {{nodes: [{{address: '{address}'}}]}}}}"
+            ));
+
+            assert!(
+                matches!(
+                    config.validate().unwrap_err().current_context(),
+                    Error::InvalidNodeAddress
+                ),
+                "address: {address}"
+            );
+        }
+    }
+
+    #[test]
+    fn conflicting_authorization() {
+        let config = config(
+            "
+address: 0.0.0.0:9000
+cluster:
+  nodes: [{address: 127.0.0.1:8000}]
+  credentials: {login: admin, password: password}
+  token: secret
+",
+        );
+
+        assert!(matches!(
+            config.validate().unwrap_err().current_context(),
+            Error::ConflictingAuthorization
+        ));
+    }
+
+    #[test]
+    fn log_format() {
+        let config = config(
+            "
+address: 0.0.0.0:9000
+logger:
+  file: {enabled: true, log-file: /tmp/bob.log, format: json}
+  stdout: {enabled: true}
+",
+        );
+
+        assert_eq!(config.logger.filter, "info");
+        assert_eq!(config.logger.file.unwrap().format, LogFormat::Json);
+        assert_eq!(config.logger.stdout.unwrap().format, LogFormat::Text);
+        assert!(serde_yaml::from_str::<LogFormat>("xml").is_err());
+    }
+
+    #[test]
+    fn log_filters() {
+        let config = config(
+            "
+address: 0.0.0.0:9000
+logger:
+  trace-level: DEBUG
+  file: {enabled: true, log-file: /tmp/bob.log, filter: debug}
+  stdout: {enabled: true, filter: 'warn,bob_management=info'}
+",
+        );
+
+        assert_eq!(config.logger.filter, "DEBUG");
+        assert_eq!(config.logger.file.unwrap().filter.as_deref(), Some("debug"));
+        assert_eq!(
+            config.logger.stdout.unwrap().filter.as_deref(),
+            Some("warn,bob_management=info")
+        );
+    }
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(OsString, OsString)> {
+        vars.iter()
+            .map(|(var, value)| ((*var).into(), (*value).into()))
+            .collect()
+    }
+
+    #[test]
+    fn env_overrides() {
+        let config = Config::load(
+            None,
+            None,
+            vars(&[
+                ("BOBGUI_ADDRESS", "127.0.0.1:9000"),
+                ("BOBGUI_REQUEST_TIMEOUT", "10s"),
+                ("BOBGUI_LOGGER__TRACE_LEVEL", "debug"),
+                ("BOBGUI_CLUSTER__NODES", "[{address: 'node1.bob:8000'}]"),
+                ("BOBGUI_CLUSTER__CREDENTIALS__LOGIN", "admin"),
+                ("BOBGUI_CLUSTER__CREDENTIALS__PASSWORD", "12345"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(config.address, SocketAddr::from(([127, 0, 0, 1], 9000)));
+        assert_eq!(config.request_timeout, Duration::from_secs(10));
+        assert_eq!(config.shutdown_timeout, Config::default_shutdown_timeout());
+        assert_eq!(config.logger.filter, "debug");
+        let cluster = config.cluster.unwrap();
+        assert_eq!(cluster.nodes[0].address, "node1.bob:8000");
+        assert_eq!(cluster.credentials.unwrap().password, "12345");
+
+        let file = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/../config.yaml"));
+        let config = Config::load(
+            Some(file),
+            None,
+            vars(&[("BOBGUI_LOGGER__STDOUT__ENABLED", "false")]),
+        )
+        .unwrap();
+        assert_eq!(config.address, SocketAddr::from(([0, 0, 0, 0], 9000)));
+        assert!(config.logger.file.unwrap().enabled);
+        assert!(!config.logger.stdout.unwrap().enabled);
+
+        let error =
+            Config::load(None, None, vars(&[("BOBGUI_METRICS__SAMPLES", "many")])).unwrap_err();
+        assert!(matches!(error.current_context(), Error::InvalidEnv));
+    }
+
+    #[test]
+    fn default_config() {
+        let config: Config = serde_yaml::from_str(DEFAULT_CONFIG).unwrap();
+
+        assert_eq!(
+            serde_yaml::to_value(config).unwrap(),
+            serde_yaml::to_value(Config::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn config_diff() {
+        let old = config(
+            "{address: 0.0.0.0:9000, cluster: {nodes: [{address: 'node1:8000'}], token: a}}",
+        );
+        let new = config(
+            "
+address: 0.0.0.0:9100
+logger: {filter: debug}
+cluster: {nodes: [{address: 'node1:8000'}, {address: 'node2:8000'}], token: b}
+tls: {cert: cert.pem, key: key.pem}
+",
+        );
+
+        let changes: Vec<_> = old.diff(&new).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            changes,
+            [
+                r#"address: "0.0.0.0:9000" -> "0.0.0.0:9100""#,
+                r#"logger.filter: "info" -> "debug""#,
+                r#"cluster.nodes: [{"address":"node1:8000","grpc-port":null}] -> [{"address":"node1:8000","grpc-port":null},{"address":"node2:8000","grpc-port":null}]"#,
+                r#"cluster.token: "*****" -> "*****""#,
+                r#"tls: null -> {"cert":"cert.pem","key":"key.pem","redirect-http":null}"#,
+            ]
+        );
+        assert!(new.diff(&new).is_empty());
+    }
+
+    #[test]
+    fn metrics_defaults() {
+        let config = config("address: 0.0.0.0:9000");
+
+        assert_eq!(config.metrics.scrape_interval, Duration::from_secs(10));
+        assert_eq!(config.metrics.samples, 60);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn invalid_metrics() {
+        for metrics in ["{scrape-interval: 0s}", "{samples: 1}"] {
+            let config = config(&format!("{{address: 0.0.0.0:9000, metrics: {metrics}}}"));
+
+            assert!(
+                matches!(
+                    config.validate().unwrap_err().current_context(),
+                    Error::InvalidMetrics
+                ),
+                "metrics: {metrics}"
+            );
+        }
+    }
+
+    #[test]
+    fn cors_section() {
+        let cors = config(
+            "
+address: 0.0.0.0:9000
+cors:
+  origins: ['http://localhost:3000', 'https://*.example.com']
+  methods: [GET, POST]
+  credentials: true
+  max-age: 10m
+",
+        )
+        .cors;
+
+        assert!(!cors.allow_all);
+        assert_eq!(
+            cors.origins,
+            ["http://localhost:3000", "https://*.example.com"]
+        );
+        assert_eq!(cors.methods, ["GET", "POST"]);
+        assert_eq!(cors.headers, CorsConfig::default_headers());
+        assert!(cors.credentials);
+        assert_eq!(cors.max_age, Some(Duration::from_secs(600)));
+        assert!(cors.validate().is_ok());
+    }
+
+    #[test]
+    fn cors_allow_all() {
+        for yaml in [
+            "{address: 0.0.0.0:9000, cors-allow-all: true}",
+            "{address: 0.0.0.0:9000, cors: true}",
+            "{address: 0.0.0.0:9000, cors: {allow-all: true}}",
+        ] {
+            let cors = config(yaml).cors;
+            assert!(cors.allow_all, "{yaml}");
+            assert_eq!(cors.methods, CorsConfig::default_methods(), "{yaml}");
+        }
+        assert_eq!(
+            config("{address: 0.0.0.0:9000, cors-allow-all: false}").cors,
+            CorsConfig::default()
+        );
+
+        let config = Config::load(None, None, vars(&[("BOBGUI_CORS_ALLOW_ALL", "true")])).unwrap();
+        assert!(config.cors.allow_all);
+    }
+
+    #[test]
+    fn invalid_cors() {
+        let config = config(
+            "
+address: 0.0.0.0:9000
+cors:
+  origins: ['*', 'http://localhost:3000/', 'localhost']
+  methods: [GET, 'NOT GET']
+  headers: ['*', '']
+",
+        );
+        let error = config.validate().unwrap_err();
+
+        assert!(matches!(error.current_context(), Error::InvalidCors));
+        let fields: Vec<_> = error
+            .frames()
+            .filter_map(|frame| frame.downcast_ref::<String>())
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "cors.origins[1]: http://localhost:3000/",
+                "cors.origins[2]: localhost",
+                "cors.methods[1]: NOT GET",
+                "cors.headers[1]: ",
+            ]
+        );
+    }
+
+    #[test]
+    fn env_strings() {
+        for secret in [
+            "0x1F", "1.50", "1e3", "~", "true", "null", "[1]", "{a: b}", "12345",
+        ] {
+            let config = Config::load(
+                None,
+                None,
+                vars(&[
+                    ("BOBGUI_CLUSTER__NODES", "[{address: 'node1.bob:8000'}]"),
+                    ("BOBGUI_CLUSTER__CREDENTIALS__LOGIN", "007"),
+                    ("BOBGUI_CLUSTER__CREDENTIALS__PASSWORD", secret),
+                    ("BOBGUI_CLUSTER__TOKEN", secret),
+                ]),
+            )
+            .unwrap();
+
+            let cluster = config.cluster.unwrap();
+            let credentials = cluster.credentials.unwrap();
+            assert_eq!(credentials.login, "007");
+            assert_eq!(credentials.password, secret);
+            assert_eq!(cluster.token.unwrap(), secret);
+        }
+
+        let config = Config::load(
+            None,
+            None,
+            vars(&[
+                ("BOBGUI_METRICS__SAMPLES", "30"),
+                ("BOBGUI_LOGGER__STDOUT__ENABLED", "true"),
+                ("BOBGUI_LOGGER__FILE__ENABLED", "false"),
+                ("BOBGUI_LOGGER__FILE__LOG_SIZE", "0x1F"),
+                ("BOBGUI_CORS_ALLOW_ALL", "true"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(config.metrics.samples, 30);
+        assert!(config.logger.stdout.unwrap().enabled);
+        assert_eq!(config.logger.file.unwrap().log_size, 31);
+        assert!(config.cors.allow_all);
+    }
+
+    #[test]
+    fn template_fields() {
+        fn assert_set(path: &str, value: &Value) {
+            match value {
+                Value::Null => panic!("{path} isn't set"),
+                Value::Mapping(mapping) => {
+                    for (key, value) in mapping {
+                        assert_set(&format!("{path}.{}", key.as_str().unwrap()), value);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        assert_set("config", &Config::template());
+    }
+}
@@ -0,0 +1,224 @@
+//! Overrides of the configuration fields from the environment
+//!
+//! Variable `BOBGUI_LOGGER__FILTER=debug` sets the `filter` field of the `logger` section:
+//! the prefix is stripped, `__` separates the nested sections and `_` stands for `-`.
+//! Values of the string fields are taken as is, the other ones are parsed as YAML,
+//! so `BOBGUI_CLUSTER__NODES='[{address: bob:8000}]'` is accepted.
+//! Variables with the `_FILE` suffix set the field to the contents of the named file,
+//! e.g. `BOBGUI_CLUSTER__CREDENTIALS__PASSWORD_FILE=/run/secrets/bob-password`
+
+use crate::config::Error;
+use error_stack::{Report, Result, ResultExt};
+use serde_yaml::{Mapping, Value};
+use std::ffi::OsString;
+
+/// Prefix of the variables overriding the configuration
+pub const PREFIX: &str = "BOBGUI_";
+
+/// Separator of the nested sections in the variable names
+const SEPARATOR: &str = "__";
+
+/// Suffix of the variables with the path to the file containing the value, `_FILE` before conversion
+const FILE_SUFFIX: &str = "-file";
+
+/// Fields whose names end with `-file`, set from their variables as is
+const FILE_FIELDS: &[&str] = &["log-file"];
+
+/// Former names of the fields, as `(alias, field)`
+const ALIASES: &[(&str, &str)] = &[("trace-level", "filter"), ("cors-allow-all", "cors")];
+
+/// Value of a configuration field taken from the environment
+#[derive(Debug, Clone, PartialEq)]
+pub struct Override {
+    /// Name of the variable
+    pub var: String,
+
+    /// Path of the field, e.g. `[logger, filter]`
+    pub path: Vec<String>,
+
+    pub value: Value,
+}
+
+/// Collects the overrides from the `vars` with the [`PREFIX`], ignoring the rest
+///
+/// # Errors
+///
+/// The function will fail if a variable's value isn't valid unicode,
+/// its name has empty sections or the file it names couldn't be read
+pub fn overrides(
+    vars: impl IntoIterator<Item = (OsString, OsString)>,
+) -> Result<Vec<Override>, Error> {
+    let mut overrides = vec![];
+    for (var, value) in vars {
+        let Some(var) = var.to_str().filter(|var| var.starts_with(PREFIX)) else {
+            continue;
+        };
+        let value = value
+            .into_string()
+            .map_err(|_| Report::new(Error::InvalidEnv))
+            .attach_printable_lazy(|| format!("{var}: value isn't valid unicode"))?;
+        overrides.push(Override::new(var, value)?);
+    }
+    overrides.sort_by(|a, b| a.var.cmp(&b.var));
+
+    Ok(overrides)
+}
+
+/// Sets the fields of the `config` to the values of the `overrides`,
+/// creating the missing sections.
+/// The `template` has every field set and shows which of them are strings, see [`typed`]
+pub fn apply(config: &mut Value, overrides: &[Override], template: &Value) {
+    for Override { path, value, .. } in overrides {
+        let mut field = &mut *config;
+        let mut field_template = Some(template);
+        for key in path {
+            field_template = field_template.and_then(|template| template.get(key));
+            if !field.is_mapping() {
+                *field = Value::Mapping(Mapping::new());
+            }
+            let Value::Mapping(section) = field else {
+                unreachable!()
+            };
+            for (alias, _) in ALIASES.iter().filter(|(_, field)| field == key) {
+                section.remove(*alias);
+            }
+            field = section.entry(key.as_str().into()).or_insert(Value::Null);
+        }
+        *field = typed(value, field_template);
+    }
+}
+
+/// Value of the variable fitting the field with the `template` value.
+/// Strings are kept as is, so that secrets like `0x1F` or `~` aren't altered,
+/// the values of the other fields are parsed as YAML. Values of the unknown fields
+/// are parsed only if they look like a sequence or a mapping
+fn typed(value: &Value, template: Option<&Value>) -> Value {
+    let Value::String(text) = value else {
+        return value.clone();
+    };
+    match template {
+        Some(Value::String(_)) => value.clone(),
+        None if !text.starts_with(['[', '{']) => value.clone(),
+        _ => serde_yaml::from_str(text).unwrap_or_else(|_| value.clone()),
+    }
+}
+
+impl Override {
+    fn new(var: &str, value: String) -> Result<Self, Error> {
+        let mut path: Vec<String> = var[PREFIX.len()..]
+            .split(SEPARATOR)
+            .map(|key| key.to_lowercase().replace('_', "-"))
+            .collect();
+        let secret = path.last().is_some_and(|field| {
+            field.ends_with(FILE_SUFFIX) && !FILE_FIELDS.contains(&field.as_str())
+        });
+        if let Some(field) = path.last_mut().filter(|_| secret) {
+            field.truncate(field.len() - FILE_SUFFIX.len());
+        }
+        if path.iter().any(String::is_empty) {
+            return Err(Error::InvalidEnv)
+                .attach_printable_lazy(|| format!("{var}: empty field name"));
+        }
+        for key in &mut path {
+            if let Some((_, field)) = ALIASES.iter().find(|(alias, _)| alias == key) {
+                *key = (*field).to_string();
+            }
+        }
+
+        let value = if secret {
+            let contents = std::fs::read_to_string(&value)
+                .change_context(Error::InvalidEnv)
+                .attach_printable_lazy(|| format!("{var}: couldn't read {value}"))?;
+            Value::String(contents.trim_end_matches(['\r', '\n']).to_string())
+        } else {
+            Value::String(value)
+        };
+
+        Ok(Self {
+            var: var.to_string(),
+            path,
+            value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(OsString, OsString)> {
+        vars.iter()
+            .map(|(var, value)| ((*var).into(), (*value).into()))
+            .collect()
+    }
+
+    #[test]
+    fn nested_fields() {
+        let overrides = overrides(vars(&[
+            ("BOBGUI_LOGGER__TRACE_LEVEL", "debug"),
+            ("BOBGUI_ADDRESS", "0.0.0.0:9000"),
+            ("BOBGUI_METRICS__SAMPLES", "30"),
+            ("BOBGUI_LOGGER__FILE__LOG_FILE", "/tmp/bob.log"),
+            ("HOME", "/root"),
+        ]))
+        .unwrap();
+        let mut config: Value =
+            serde_yaml::from_str("{address: 0.0.0.0:7000, logger: {trace-level: info}}").unwrap();
+        let template = serde_yaml::from_str(
+            "{address: '', logger: {filter: '', file: {log-file: ''}}, metrics: {samples: 0}}",
+        )
+        .unwrap();
+        apply(&mut config, &overrides, &template);
+
+        assert_eq!(
+            config,
+            serde_yaml::from_str::<Value>(
+                "
+address: 0.0.0.0:9000
+logger:
+  filter: debug
+  file: {log-file: /tmp/bob.log}
+metrics: {samples: 30}
+"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn secret_file() {
+        let path =
+            std::env::temp_dir().join(format!("bob-management-password-{}", std::process::id()));
+        std::fs::write(&path, "12345\n").unwrap();
+
+        let overrides = overrides(vars(&[(
+            "BOBGUI_CLUSTER__CREDENTIALS__PASSWORD_FILE",
+            path.to_str().unwrap(),
+        )]))
+        .unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(overrides[0].path, ["cluster", "credentials", "password"]);
+        assert_eq!(overrides[0].value, Value::String("12345".to_string()));
+    }
+
+    #[test]
+    fn invalid_vars() {
+        for (var, value) in [
+            ("BOBGUI_LOGGER____FILTER", "debug"),
+            ("BOBGUI_", "debug"),
+            ("BOBGUI_TOKEN_FILE", "/nonexistent/token"),
+        ] {
+            assert!(
+                matches!(
+                    overrides(vars(&[(var, value)]))
+                        .unwrap_err()
+                        .current_context(),
+                    Error::InvalidEnv
+                ),
+                "{var}"
+            );
+        }
+    }
+}
@@ -0,0 +1,405 @@
+//! Formats of the configuration files
+
+use crate::config::Error;
+use error_stack::{Report, Result};
+use serde::de::{
+    DeserializeOwned, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor,
+};
+use std::path::Path;
+
+/// Format of the configuration file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConfigFormat {
+    #[default]
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Format of the file with the `path`, by its extension
+    #[must_use]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "yaml" | "yml" => Some(Self::Yaml),
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    /// Explicitly specified `format` or the one of the file with the `path`, YAML by default
+    #[must_use]
+    pub fn for_file(path: &Path, format: Option<Self>) -> Self {
+        format.or_else(|| Self::from_path(path)).unwrap_or_default()
+    }
+
+    /// Parses the `text` in this format
+    ///
+    /// # Errors
+    ///
+    /// The function will fail if the `text` isn't valid in this format or doesn't fit `T`.
+    /// The error is attached with the message and the location of the problem
+    pub fn parse<T: DeserializeOwned>(self, text: &str) -> Result<T, Error> {
+        let (message, location) = match self {
+            Self::Yaml => match serde_yaml::from_str(text) {
+                Ok(value) => return Ok(value),
+                Err(err) => (
+                    err.to_string(),
+                    err.location()
+                        .map(|location| (location.line(), location.column())),
+                ),
+            },
+            Self::Toml => match toml::from_str(text) {
+                Ok(value) => return Ok(value),
+                Err(err) => (
+                    err.message().to_string(),
+                    err.span().map(|span| line_column(text, span.start)),
+                ),
+            },
+            Self::Json => match serde_json::from_str(text) {
+                Ok(value) => return Ok(value),
+                Err(err) => (err.to_string(), Some((err.line(), err.column()))),
+            },
+        };
+
+        let mut report = Report::new(Error::Parse(self));
+        if let Some((line, column)) = location {
+            report = report.attach_printable(format!("line {line}, column {column}"));
+            // YAML and JSON errors end with the location themselves
+            let suffix = format!(" at line {line} column {column}");
+            let message = message.strip_suffix(&suffix).unwrap_or(&message);
+            return Err(report.attach_printable(message.to_string()));
+        }
+
+        Err(report.attach_printable(message))
+    }
+
+    /// One-based line and column of the field's value in the `text`, if it's there
+    #[must_use]
+    pub fn locate(self, text: &str, path: &FieldPath) -> Option<(usize, usize)> {
+        if path.0.is_empty() {
+            return None;
+        }
+        let probe = Probe(&path.0);
+        match self {
+            Self::Yaml => {
+                let err = probe
+                    .deserialize(serde_yaml::Deserializer::from_str(text))
+                    .err()?;
+                let location = err.location()?;
+                found(&err.to_string()).then_some((location.line(), location.column()))
+            }
+            Self::Toml => {
+                let err = probe.deserialize(toml::Deserializer::new(text)).err()?;
+                let span = err.span()?;
+                found(err.message()).then(|| line_column(text, span.start))
+            }
+            Self::Json => {
+                let err = probe
+                    .deserialize(&mut serde_json::Deserializer::from_str(text))
+                    .err()?;
+                found(&err.to_string()).then_some((err.line(), err.column()))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Yaml => write!(f, "YAML"),
+            Self::Toml => write!(f, "TOML"),
+            Self::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+/// Path of a configuration field as it's written in the file, such as `cluster.nodes[0].address`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FieldPath(Vec<Segment>);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+impl FieldPath {
+    /// This path followed by the `other` one
+    #[must_use]
+    pub fn join(&self, other: &Self) -> Self {
+        Self(self.0.iter().chain(&other.0).cloned().collect())
+    }
+}
+
+impl From<&str> for FieldPath {
+    fn from(path: &str) -> Self {
+        let mut segments = vec![];
+        for part in path.split('.') {
+            let mut indices = part.split('[');
+            if let Some(key) = indices.next().filter(|key| !key.is_empty()) {
+                segments.push(Segment::Key(key.to_string()));
+            }
+            segments.extend(
+                indices
+                    .filter_map(|index| index.strip_suffix(']')?.parse().ok())
+                    .map(Segment::Index),
+            );
+        }
+
+        Self(segments)
+    }
+}
+
+/// What the deserializer is told to expect at the probed field, so that its error names it
+const PROBED: &str = "the probed field";
+
+/// Whether the error `message` was raised at the probed field
+fn found(message: &str) -> bool {
+    message.contains(PROBED)
+}
+
+/// Walks the document down the remaining path and fails at its end,
+/// so that the format's error carries the location of the field
+struct Probe<'a>(&'a [Segment]);
+
+/// Rejects any value, see [`PROBED`]
+struct Target;
+
+impl<'de> Visitor<'de> for Target {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str(PROBED)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Probe<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<(), D::Error> {
+        if self.0.is_empty() {
+            deserializer.deserialize_any(Target)
+        } else {
+            deserializer.deserialize_any(self)
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for Probe<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a table or a list")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<(), A::Error> {
+        let key = match self.0.split_first() {
+            Some((Segment::Key(key), rest)) => Some((key, rest)),
+            _ => None,
+        };
+        while let Some(current) = map.next_key::<String>()? {
+            match key {
+                Some((key, rest)) if *key == current => map.next_value_seed(Probe(rest))?,
+                _ => map.next_value::<IgnoredAny>().map(|_| ())?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<(), A::Error> {
+        if let Some((Segment::Index(index), rest)) = self.0.split_first() {
+            for _ in 0..*index {
+                if seq.next_element::<IgnoredAny>()?.is_none() {
+                    return Ok(());
+                }
+            }
+            seq.next_element_seed(Probe(rest))?;
+        }
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+
+        Ok(())
+    }
+}
+
+/// One-based line and column of the byte `offset` in the `text`
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::config::{Config, CorsConfig, FileLogger, LogFormat, LoggerConfig};
+    use error_stack::{AttachmentKind, FrameKind};
+    use std::time::Duration;
+
+    fn attachments<C>(report: &Report<C>) -> Vec<String> {
+        report
+            .frames()
+            .filter_map(|frame| match frame.kind() {
+                FrameKind::Attachment(AttachmentKind::Printable(attachment)) => {
+                    Some(attachment.to_string())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn extensions() {
+        for (path, format) in [
+            ("config.yaml", Some(ConfigFormat::Yaml)),
+            ("config.YML", Some(ConfigFormat::Yaml)),
+            ("/etc/bob/config.toml", Some(ConfigFormat::Toml)),
+            ("config.json", Some(ConfigFormat::Json)),
+            ("config", None),
+            ("config.ini", None),
+        ] {
+            assert_eq!(ConfigFormat::from_path(Path::new(path)), format, "{path}");
+        }
+    }
+
+    #[test]
+    fn defaults() {
+        for (format, text) in [
+            (
+                ConfigFormat::Yaml,
+                "
+address: 0.0.0.0:9000
+logger:
+  file: {enabled: true}
+",
+            ),
+            (
+                ConfigFormat::Toml,
+                r#"
+address = "0.0.0.0:9000"
+
+[logger.file]
+enabled = true
+"#,
+            ),
+            (
+                ConfigFormat::Json,
+                r#"{"address": "0.0.0.0:9000", "logger": {"file": {"enabled": true}}}"#,
+            ),
+        ] {
+            let config: Config = format.parse(text).unwrap();
+            assert_eq!(
+                config.request_timeout,
+                Config::default_timeout(),
+                "{format}"
+            );
+            assert_eq!(
+                config.shutdown_timeout,
+                Config::default_shutdown_timeout(),
+                "{format}"
+            );
+            assert_eq!(config.cors, CorsConfig::default(), "{format}");
+            assert!(config.cluster.is_none(), "{format}");
+
+            let logger = config.logger;
+            assert_eq!(logger.filter, LoggerConfig::default_filter(), "{format}");
+            assert!(logger.stdout.is_none(), "{format}");
+
+            let file = logger.file.unwrap();
+            assert!(file.enabled, "{format}");
+            assert_eq!(file.log_file, None, "{format}");
+            assert_eq!(
+                file.log_amount,
+                FileLogger::default_log_amount(),
+                "{format}"
+            );
+            assert_eq!(file.log_size, FileLogger::default_log_size(), "{format}");
+            assert_eq!(file.format, LogFormat::Text, "{format}");
+            assert_eq!(file.filter, None, "{format}");
+        }
+    }
+
+    #[test]
+    fn durations() {
+        let config: Config = ConfigFormat::Toml
+            .parse("address = \"0.0.0.0:9000\"\nrequest-timeout = \"1m 30s\"")
+            .unwrap();
+
+        assert_eq!(config.request_timeout, Duration::from_secs(90));
+    }
+
+    #[test]
+    fn error_location() {
+        for (format, text, location) in [
+            (
+                ConfigFormat::Yaml,
+                "address: 0.0.0.0:9000\nmetrics:\n  samples: many\n",
+                "line 3, column 12",
+            ),
+            (
+                ConfigFormat::Toml,
+                "address = \"0.0.0.0:9000\"\n\n[metrics]\nsamples = \"many\"\n",
+                "line 4, column 11",
+            ),
+            (
+                ConfigFormat::Json,
+                "{\n  \"address\": \"0.0.0.0:9000\",\n  \"metrics\": {\"samples\": \"many\"}\n}",
+                "line 3, column 31",
+            ),
+        ] {
+            let report = format.parse::<Config>(text).unwrap_err();
+
+            assert!(
+                matches!(report.current_context(), Error::Parse(f) if *f == format),
+                "{format}"
+            );
+            assert!(report.to_string().contains(&format.to_string()), "{format}");
+            let attachments = attachments(&report);
+            assert_eq!(attachments[1], location, "{format}");
+            assert!(
+                attachments[0].contains("invalid type"),
+                "{format}: {attachments:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn field_location() {
+        let path = FieldPath::from("cluster.nodes[1].address");
+        for (format, text, location) in [
+            (
+                ConfigFormat::Yaml,
+                "cluster:\n  nodes:\n    - address: a:1\n    - address: b\n",
+                Some((4, 16)),
+            ),
+            (
+                ConfigFormat::Toml,
+                "[[cluster.nodes]]\naddress = \"a:1\"\n\n[[cluster.nodes]]\naddress = \"b\"\n",
+                Some((5, 11)),
+            ),
+            (
+                ConfigFormat::Json,
+                "{\"cluster\": {\"nodes\": [\n  {\"address\": \"a:1\"},\n  {\"address\": \"b\"}\n]}}",
+                Some((3, 17)),
+            ),
+            (ConfigFormat::Yaml, "cluster:\n  nodes: [{address: a:1}]\n", None),
+            (ConfigFormat::Yaml, "cluster: 1\n", None),
+        ] {
+            assert_eq!(format.locate(text, &path), location, "{format}: {text}");
+        }
+        assert_eq!(
+            ConfigFormat::Yaml.locate("a: 1", &FieldPath::default()),
+            None
+        );
+    }
+}
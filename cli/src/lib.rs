@@ -1,6 +1,13 @@
 pub mod cli;
 mod config;
+pub mod env;
+mod format;
 
 pub use clap::Parser;
-pub use cli::Args;
-pub use config::{Config, FileLogger, FromFile, LoggerConfig, StdoutLogger};
+pub use cli::{Args, Command, ConfigArgs};
+pub use config::{
+    AuthConfig, BootstrapNode, ClusterConfig, ClusterCredentials, Config, CorsConfig, FieldChange,
+    FileLogger, FromFile, LogFormat, LoggerConfig, MetricsConfig, StdoutLogger, TlsConfig,
+    DEFAULT_CONFIG, REDACTED,
+};
+pub use format::ConfigFormat;
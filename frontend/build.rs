@@ -62,7 +62,7 @@ pub fn build_types() {
     inputs[0].push("backend");
     output.push("src/types/rust.d.ts");
 
-    tsync::generate_typescript_defs(inputs, output, false);
+    tsync::generate_typescript_defs(inputs, output, false, false);
 }
 
 pub fn build_frontend() {
@@ -75,8 +75,10 @@ pub fn build_frontend() {
     shell("yarn build");
 }
 
+/// Copies the build output into `target/<target_profile>/frontend`,
+/// the folder next to the backend's executable it's served from
 pub fn move_frontend() {
-    let mut target = PathBuf::from(std::env::var("OUT_DIR").unwrap()); // OUT_DIR == <project_dir>/target/<target_profile>/build/bob_management-<HASH>/out
+    let mut target = PathBuf::from(std::env::var("OUT_DIR").unwrap()); // OUT_DIR == <project_dir>/target/<target_profile>/build/frontend-<HASH>/out
     target.pop();
     target.pop();
     target.pop();
@@ -119,6 +121,9 @@ fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<()>
 fn main() {
     build_types();
     build_frontend();
-    move_frontend();
+    // The backend embeds the output from where it's built
+    if std::env::var_os("CARGO_FEATURE_EMBED").is_none() {
+        move_frontend();
+    }
     println!("cargo:rerun-if-changed=./");
 }
@@ -0,0 +1,21 @@
+/* Written by hand, as `tsync` ignores the renamed fields of `ProblemDetails` */
+
+/** Error response of the API, as described in RFC 7807 */
+interface ProblemDetails {
+  /** URI of the problem type, `about:blank` as the `code` identifies the problem */
+  type: string;
+  /** Short summary of the problem, the reason phrase of the `status` */
+  title: string;
+  /** HTTP status code */
+  status: number;
+  /** Stable machine-readable code of the problem */
+  code: string;
+  /** Explanation specific to this occurrence of the problem */
+  detail?: string;
+  /** Path of the request that caused the problem */
+  instance?: string;
+  /** Name of the Bob node that caused the problem */
+  node?: string;
+  /** Id of the request, to find it in the logs */
+  request_id?: string;
+}
@@ -1 +1,239 @@
 /* This file is generated and managed by tsync */
+
+/** Bob node */
+interface Node {
+  name: string;
+  /** gRPC address of the node <host:port> */
+  address: string;
+  status: NodeStatus;
+  vdisks: Array<VDisk>;
+}
+
+/** Whether the node responds to requests */
+type NodeStatus =
+  | "online" | "offline" | "timeout";
+
+/** Virtual disk and its replicas */
+interface VDisk {
+  id: number;
+  replicas: Array<Replica>;
+}
+
+/** Location of a vdisk's replica */
+interface Replica {
+  node: string;
+  disk: string;
+  path: string;
+}
+
+/** Physical disk of the node */
+interface Disk {
+  name: string;
+  path: string;
+  status: DiskStatus;
+}
+
+/** Whether the disk is used by the node */
+type DiskStatus =
+  | "active" | "inactive";
+
+/** Partition of the vdisk on the node */
+interface Partition {
+  vdisk_id: number;
+  node: string;
+  disk: string;
+  /** Unix timestamp of the partition's start, in seconds */
+  timestamp: number;
+  records_count: number;
+  /** Size of the partition's data in bytes, absent if the node doesn't report it */
+  size?: number;
+}
+
+/** Data stored on the node on behalf of the unavailable `remote_node` */
+interface Alien {
+  remote_node: string;
+  vdisk_id: number;
+  path: string;
+  /** Size of the alien data in bytes, absent if the node doesn't report it */
+  size?: number;
+}
+
+/** Disk space usage of the node, in bytes */
+interface SpaceInfo {
+  total_disk: number;
+  free_disk: number;
+  used_disk: number;
+  /** Space occupied by Bob's data */
+  occupied_disk: number;
+  /** Space occupied by Bob's data on each disk */
+  occupied_by_disk: Record<string, number>;
+}
+
+/** Health of the vdisk, based on availability of its replicas */
+type VDiskStatus =
+  | "good" | "degraded" | "offline";
+
+/** Whole-cluster overview */
+interface ClusterInfo {
+  nodes_count: number;
+  nodes_online: number;
+  /** Names of the nodes that didn't respond within the request timeout */
+  nodes_timed_out: Array<string>;
+  vdisks: VDisksCount;
+  /** Disk space usage of the online nodes */
+  space: ClusterSpace;
+  versions: Array<NodeVersion>;
+}
+
+/** Number of vdisks of each [`VDiskStatus`] */
+interface VDisksCount {
+  good: number;
+  degraded: number;
+  offline: number;
+}
+
+/** Disk space usage of the cluster, in bytes */
+interface ClusterSpace {
+  total_disk: number;
+  used_disk: number;
+  free_disk: number;
+}
+
+/** Versions of the node's software, absent for the offline nodes */
+interface NodeVersion {
+  node: string;
+  bob_version?: string;
+  pearl_version?: string;
+}
+
+/** Detailed state of a single node */
+interface NodeDetails {
+  name: string;
+  /** gRPC address of the node <host:port>, absent for the offline node */
+  address?: string;
+  status: NodeStatus;
+  disks: Array<Disk>;
+  /** Vdisks which replicas are hosted by the node */
+  vdisks: Array<VDisk>;
+  aliens: Array<Alien>;
+  space?: SpaceInfo;
+  version?: NodeVersion;
+}
+
+/** Partitions of the vdisk's replicas stored on the node */
+interface ReplicaPartitions {
+  node: string;
+  status: NodeStatus;
+  /** Partitions ordered by timestamp, empty for the offline node */
+  partitions: Array<Partition>;
+}
+
+/** Result of the partitions deletion */
+interface PartitionsDeletion {
+  deleted: Array<Partition>;
+  /** Partitions the nodes failed to delete */
+  failed: Array<Partition>;
+  /**
+   * Problems of the replica nodes that couldn't be reached,
+   * whose partitions are left as they are
+   */
+  unreached: Array<ProblemDetails>;
+}
+
+/** Aliens stored on the node */
+interface NodeAliens {
+  node: string;
+  status: NodeStatus;
+  /** Empty for the offline node */
+  aliens: Array<Alien>;
+}
+
+/** Aliens waiting to be pushed back to the `remote_node` */
+interface RemoteNodeAliens {
+  remote_node: string;
+  /** Number of vdisks with pending aliens */
+  vdisks_count: number;
+  /** Size of the pending aliens in bytes, of those which size is reported */
+  size: number;
+  /**
+   * Number of vdisks with pending aliens whose size the node didn't report,
+   * they aren't included in `size`
+   */
+  unsized_vdisks_count: number;
+}
+
+/** Whole-cluster summary of the pending aliens */
+interface AliensSummary {
+  /** Number of vdisks with pending aliens, across all nodes */
+  vdisks_count: number;
+  /** Size of the pending aliens in bytes, of those which size is reported */
+  size: number;
+  /**
+   * Number of vdisks with pending aliens whose size the node didn't report,
+   * they aren't included in `size`
+   */
+  unsized_vdisks_count: number;
+  /** Pending aliens grouped by the node they belong to, ordered by its name */
+  remote_nodes: Array<RemoteNodeAliens>;
+  /** Nodes that couldn't report their aliens */
+  unavailable_nodes: Array<string>;
+}
+
+/** Metrics of the node at the moment of scraping */
+interface MetricsSample {
+  /** Unix timestamp of the scrape, in milliseconds */
+  timestamp: number;
+  counters: Record<string, number>;
+  gauges: Record<string, number>;
+  /**
+   * Per-second increase of the counters since the previous sample,
+   * empty for the first sample and missing for the counters that were reset
+   */
+  rates: Record<string, number>;
+}
+
+/** Latest metrics samples of the node, ordered by time */
+interface NodeMetrics {
+  node: string;
+  samples: Array<MetricsSample>;
+}
+
+/** Latest metrics of every node, summed across the cluster */
+interface ClusterMetrics {
+  /** Number of nodes that have been scraped at least once */
+  nodes_count: number;
+  counters: Record<string, number>;
+  gauges: Record<string, number>;
+  rates: Record<string, number>;
+}
+
+/** Active filter directives of a log output */
+interface LogFilter {
+  /** `file` or `stdout` */
+  output: string;
+  directives: string;
+  /** Directives specified in the configuration */
+  configured: string;
+  /** Unix timestamp in milliseconds when the configured directives are restored */
+  revert_at?: number;
+}
+
+/** Change of the log outputs' filter directives */
+interface LogFilterUpdate {
+  /** Output to change, every output is changed if not specified */
+  output?: string;
+  directives: string;
+  /** Seconds after which the configured directives are restored, a week at most */
+  revert_after?: number;
+}
+
+/** Address of one of the cluster's nodes and the Bob user's credentials */
+interface LoginRequest {
+  /**
+   * REST API address of the node <host:port>, accepted only if `auth.allow-hostname` is set
+   * and no cluster is configured
+   */
+  hostname?: string;
+  login: string;
+  password: string;
+}
@@ -15,5 +15,5 @@ fn main() {
     let args = Args::parse();
 
     let doc = bob_management::ApiDoc::openapi().to_yaml().unwrap();
-    let _ = fs::write(args.filename, doc).expect("Couldn't write schema to file");
+    fs::write(args.filename, doc).expect("Couldn't write schema to file");
 }